@@ -0,0 +1,108 @@
+use anyhow::{bail, Result};
+use argh::FromArgs;
+use darknet::{cfg::Config, weights::Weights};
+use std::{fs, path::PathBuf};
+
+/// Utilities for model config and weights files.
+#[derive(Debug, Clone, FromArgs)]
+struct Args {
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Clone, FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Retarget(Retarget),
+    Partial(Partial),
+}
+
+/// Change the number of classes of a detector config.
+#[derive(Debug, Clone, FromArgs)]
+#[argh(subcommand, name = "retarget")]
+struct Retarget {
+    /// the model config file, which usually has a .cfg extension.
+    #[argh(option)]
+    model_cfg: PathBuf,
+    /// the number of classes.
+    #[argh(option)]
+    classes: Option<usize>,
+    /// the file including label names per class, used to count the classes.
+    #[argh(option)]
+    label_file: Option<PathBuf>,
+    /// the output config file.
+    #[argh(option)]
+    output: PathBuf,
+}
+
+/// Keep the weights of the first layers, for training on top of a pretrained backbone.
+#[derive(Debug, Clone, FromArgs)]
+#[argh(subcommand, name = "partial")]
+struct Partial {
+    /// the model config file, which usually has a .cfg extension.
+    #[argh(option)]
+    model_cfg: PathBuf,
+    /// the full weights file.
+    #[argh(option)]
+    weights: PathBuf,
+    /// the number of leading layers to keep.
+    #[argh(option)]
+    cutoff: usize,
+    /// the output weights file.
+    #[argh(option)]
+    output: PathBuf,
+}
+
+fn main() -> Result<()> {
+    let Args { command } = argh::from_env();
+
+    match command {
+        Command::Retarget(args) => retarget(args),
+        Command::Partial(args) => partial(args),
+    }
+}
+
+fn retarget(args: Retarget) -> Result<()> {
+    let Retarget {
+        model_cfg,
+        classes,
+        label_file,
+        output,
+    } = args;
+
+    let classes = match (classes, label_file) {
+        (Some(classes), None) => classes,
+        (None, Some(label_file)) => fs::read_to_string(label_file)?.lines().count(),
+        _ => bail!("expect exactly one of --classes and --label-file"),
+    };
+
+    let mut config = Config::open(model_cfg)?;
+    config.retarget_classes(classes)?;
+    fs::write(&output, config.to_string())?;
+
+    println!("wrote {} with {} classes", output.display(), classes);
+    Ok(())
+}
+
+fn partial(args: Partial) -> Result<()> {
+    let Partial {
+        model_cfg,
+        weights,
+        cutoff,
+        output,
+    } = args;
+
+    let config = Config::open(model_cfg)?;
+    let mut weights = Weights::open(weights, &config)?;
+    weights.truncate(cutoff);
+    // like `darknet partial`, start the training counter over
+    weights.header.seen = 0;
+    weights.save(&output)?;
+
+    println!(
+        "wrote {} with weights of {} layers",
+        output.display(),
+        weights.layers.len()
+    );
+    Ok(())
+}
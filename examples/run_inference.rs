@@ -1,6 +1,6 @@
 use anyhow::Result;
 use argh::FromArgs;
-use darknet::{BBox, Image, Network};
+use darknet::{BBox, Image, Labels, Network, PredictOptions};
 use image::RgbImage;
 use std::{
     convert::TryFrom,
@@ -46,11 +46,9 @@ fn main() -> Result<()> {
     } = argh::from_env();
 
     // Load network & labels
-    let object_labels = std::fs::read_to_string(label_file)?
-        .lines()
-        .map(ToOwned::to_owned)
-        .collect::<Vec<_>>();
+    let object_labels = Labels::open(label_file)?;
     let mut net = Network::load(model_cfg, Some(weights), false)?;
+    object_labels.check(&net)?;
 
     for image_path in input_images {
         // prepare data
@@ -62,7 +60,7 @@ fn main() -> Result<()> {
         fs::create_dir_all(&curr_output_dir)?;
 
         // Run object detection
-        let detections = net.predict(&image, 0.25, 0.5, 0.45, true);
+        let detections = net.predict_with(&image, &PredictOptions::new().letter_box(true));
 
         // show results
         println!("# {}", image_path.display());
@@ -71,8 +69,8 @@ fn main() -> Result<()> {
             .iter()
             .filter(|det| det.objectness() > objectness_threshold)
             .flat_map(|det| {
-                det.best_class(Some(class_prob_threshold))
-                    .map(|(class_index, prob)| (det, prob, &object_labels[class_index]))
+                det.best_class_label(&object_labels, Some(class_prob_threshold))
+                    .map(|(label, prob)| (det, prob, label))
             })
             .enumerate()
             .for_each(|(index, (det, prob, label))| {
@@ -0,0 +1,249 @@
+use crate::BBox;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// A bounding box in center x, center y, width and height (cxcywh) format.
+///
+/// The coordinates are either normalized by the image size, like [BBox] returned by
+/// detections, or in pixels. [BoundingBox::to_pixels] and [BoundingBox::to_normalized]
+/// convert between the two.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BoundingBox {
+    /// The x coordinate of the box center.
+    pub x: f32,
+    /// The y coordinate of the box center.
+    pub y: f32,
+    /// The box width.
+    pub w: f32,
+    /// The box height.
+    pub h: f32,
+}
+
+impl BoundingBox {
+    /// Create a box from the center point and size.
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    /// Create a box from the left, top, right and bottom edges (xyxy format).
+    pub fn from_xyxy(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self {
+            x: (left + right) / 2.0,
+            y: (top + bottom) / 2.0,
+            w: right - left,
+            h: bottom - top,
+        }
+    }
+
+    /// Create a box from the left and top edges and the size (xywh format).
+    pub fn from_xywh(left: f32, top: f32, w: f32, h: f32) -> Self {
+        Self {
+            x: left + w / 2.0,
+            y: top + h / 2.0,
+            w,
+            h,
+        }
+    }
+
+    /// Get the \[left, top, right, bottom\] edges (xyxy format).
+    pub fn to_xyxy(&self) -> [f32; 4] {
+        [self.left(), self.top(), self.right(), self.bottom()]
+    }
+
+    /// Get the \[left, top, width, height\] values (xywh format).
+    pub fn to_xywh(&self) -> [f32; 4] {
+        [self.left(), self.top(), self.w, self.h]
+    }
+
+    /// Get the \[center x, center y, width, height\] values (cxcywh format).
+    pub fn to_cxcywh(&self) -> [f32; 4] {
+        [self.x, self.y, self.w, self.h]
+    }
+
+    /// Get the left edge.
+    pub fn left(&self) -> f32 {
+        self.x - self.w / 2.0
+    }
+
+    /// Get the top edge.
+    pub fn top(&self) -> f32 {
+        self.y - self.h / 2.0
+    }
+
+    /// Get the right edge.
+    pub fn right(&self) -> f32 {
+        self.x + self.w / 2.0
+    }
+
+    /// Get the bottom edge.
+    pub fn bottom(&self) -> f32 {
+        self.y + self.h / 2.0
+    }
+
+    /// Convert a normalized box to pixel coordinates of an image of the given size.
+    pub fn to_pixels(&self, image_width: usize, image_height: usize) -> Self {
+        let (width, height) = (image_width as f32, image_height as f32);
+        Self {
+            x: self.x * width,
+            y: self.y * height,
+            w: self.w * width,
+            h: self.h * height,
+        }
+    }
+
+    /// Convert a box in pixel coordinates of an image of the given size to a normalized box.
+    pub fn to_normalized(&self, image_width: usize, image_height: usize) -> Self {
+        let (width, height) = (image_width as f32, image_height as f32);
+        Self {
+            x: self.x / width,
+            y: self.y / height,
+            w: self.w / width,
+            h: self.h / height,
+        }
+    }
+
+    /// Clamp the edges of the box into \[0, max_x\] and \[0, max_y\].
+    ///
+    /// Use `clamp(1.0, 1.0)` for normalized boxes, or the image size for boxes in pixels.
+    pub fn clamp(&self, max_x: f32, max_y: f32) -> Self {
+        Self::from_xyxy(
+            self.left().max(0.0).min(max_x),
+            self.top().max(0.0).min(max_y),
+            self.right().max(0.0).min(max_x),
+            self.bottom().max(0.0).min(max_y),
+        )
+    }
+
+    /// Get the area of the box.
+    pub fn area(&self) -> f32 {
+        self.w * self.h
+    }
+
+    /// Get the area of the intersection with another box.
+    pub fn intersection(&self, other: &Self) -> f32 {
+        let w = self.right().min(other.right()) - self.left().max(other.left());
+        let h = self.bottom().min(other.bottom()) - self.top().max(other.top());
+        if w < 0.0 || h < 0.0 {
+            0.0
+        } else {
+            w * h
+        }
+    }
+
+    /// Get the area of the union with another box.
+    pub fn union(&self, other: &Self) -> f32 {
+        self.area() + other.area() - self.intersection(other)
+    }
+
+    /// Get the intersection over union (IoU) with another box.
+    ///
+    /// It returns 0 if the boxes do not overlap or both are empty.
+    pub fn iou(&self, other: &Self) -> f32 {
+        let intersection = self.intersection(other);
+        let union = self.union(other);
+        if intersection == 0.0 || union == 0.0 {
+            0.0
+        } else {
+            intersection / union
+        }
+    }
+
+    /// Get the generalized IoU (GIoU) with another box, which is in \[-1, 1\].
+    pub fn giou(&self, other: &Self) -> f32 {
+        let iou = self.iou(other);
+        let enclosing = self.enclosing(other).area();
+        if enclosing == 0.0 {
+            return iou;
+        }
+        iou - (enclosing - self.union(other)) / enclosing
+    }
+
+    /// Get the distance IoU (DIoU) with another box.
+    ///
+    /// It is the IoU minus the squared center distance over the squared diagonal of the
+    /// enclosing box. Unlike darknet's `box_diou`, the distance term is not raised to the
+    /// power of 0.6.
+    pub fn diou(&self, other: &Self) -> f32 {
+        let iou = self.iou(other);
+        match self.center_distance_ratio(other) {
+            Some(ratio) => iou - ratio,
+            None => iou,
+        }
+    }
+
+    /// Get the complete IoU (CIoU) with another box, which adds an aspect ratio term to
+    /// [BoundingBox::diou], treating `other` as the ground truth like darknet's `box_ciou`.
+    pub fn ciou(&self, other: &Self) -> f32 {
+        let iou = self.iou(other);
+        let ratio = match self.center_distance_ratio(other) {
+            Some(ratio) => ratio,
+            None => return iou,
+        };
+        let angle = (other.w / other.h).atan() - (self.w / self.h).atan();
+        let aspect = 4.0 / (PI * PI) * angle * angle;
+        let alpha = aspect / (1.0 - iou + aspect + 0.000001);
+        iou - (ratio + alpha * aspect)
+    }
+
+    /// Get the smallest box that encloses both boxes.
+    pub fn enclosing(&self, other: &Self) -> Self {
+        Self::from_xyxy(
+            self.left().min(other.left()),
+            self.top().min(other.top()),
+            self.right().max(other.right()),
+            self.bottom().max(other.bottom()),
+        )
+    }
+
+    /// Get the squared center distance over the squared diagonal of the enclosing box, or
+    /// [None] if the enclosing box is a point.
+    pub(crate) fn center_distance_ratio(&self, other: &Self) -> Option<f32> {
+        let enclosing = self.enclosing(other);
+        let diagonal = enclosing.w * enclosing.w + enclosing.h * enclosing.h;
+        if diagonal == 0.0 {
+            return None;
+        }
+        let (dx, dy) = (self.x - other.x, self.y - other.y);
+        Some((dx * dx + dy * dy) / diagonal)
+    }
+}
+
+impl From<BBox> for BoundingBox {
+    fn from(bbox: BBox) -> Self {
+        let BBox { x, y, w, h } = bbox;
+        Self { x, y, w, h }
+    }
+}
+
+impl From<&BBox> for BoundingBox {
+    fn from(bbox: &BBox) -> Self {
+        Self::from(*bbox)
+    }
+}
+
+impl From<BoundingBox> for BBox {
+    fn from(bbox: BoundingBox) -> Self {
+        let BoundingBox { x, y, w, h } = bbox;
+        Self { x, y, w, h }
+    }
+}
+
+/// The serde definition of [BBox], which is a foreign type.
+///
+/// Use it with `#[serde(with = "darknet::BBoxDef")]` on [BBox] fields. The box is serialized
+/// as a `{ x, y, w, h }` struct.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "BBox")]
+pub struct BBoxDef {
+    /// The x coordinate of the box center.
+    pub x: f32,
+    /// The y coordinate of the box center.
+    pub y: f32,
+    /// The box width.
+    pub w: f32,
+    /// The box height.
+    pub h: f32,
+}
@@ -0,0 +1,1173 @@
+use crate::{
+    error::Error,
+    kinds::{Activation, IoULoss, LayerType, NmsKind},
+};
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+/// A typed model of a Darknet `.cfg` file.
+///
+/// The text is read the way libdarknet reads it: whitespaces are ignored, lines starting with
+/// `#` or `;` are comments and the first occurrence of a repeated option wins. Unlike libdarknet,
+/// malformed lines and values are reported as [Error::ConfigParseError] with the line number.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    /// The `[net]` section.
+    pub net: NetConfig,
+    /// The layer sections in file order.
+    pub layers: Vec<LayerConfig>,
+}
+
+impl Config {
+    /// Parse a config file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|error| Error::ConfigOpenError {
+            path: path.to_owned(),
+            error,
+        })?;
+        String::from_utf8_lossy(&bytes).parse()
+    }
+
+    /// Set the number of classes of every `[yolo]`, `[Gaussian_yolo]` and `[region]` layer.
+    ///
+    /// The filters of the convolutional layer before each detection layer are set to match,
+    /// e.g. `(classes + 5) * masks` for `[yolo]`. The `mask` indexes are checked against `num`
+    /// and `anchors`. The `counters_per_class` option is removed since it no longer fits.
+    pub fn retarget_classes(&mut self, classes: usize) -> Result<(), Error> {
+        // work on a copy to leave the config untouched on errors
+        let mut layers = self.layers.clone();
+
+        for index in 0..layers.len() {
+            let layer_type = layers[index].layer_type();
+            let filters = match &mut layers[index] {
+                LayerConfig::Yolo(yolo) => yolo.retarget_classes(classes, 5, index)?,
+                LayerConfig::GaussianYolo(yolo) => yolo.retarget_classes(classes, 9, index)?,
+                LayerConfig::Region(region) => {
+                    region.classes = Some(classes);
+                    (classes + region.coords.unwrap_or(4) + 1) * region.num.unwrap_or(1)
+                }
+                _ => continue,
+            };
+
+            match index.checked_sub(1).map(|prev| &mut layers[prev]) {
+                Some(LayerConfig::Convolutional(conv)) => conv.filters = Some(filters),
+                _ => {
+                    return Err(Error::InvalidConfigError {
+                        reason: format!(
+                            "the layer before [{}] layer {} is not convolutional",
+                            layer_type.name(),
+                            index
+                        ),
+                    })
+                }
+            }
+        }
+
+        self.layers = layers;
+        Ok(())
+    }
+
+    /// Get the input shape tuple (width, height, channels) of the network.
+    ///
+    /// Networks declaring only `inputs=` get a shape of (1, 1, inputs).
+    pub fn input_shape(&self) -> Result<(usize, usize, usize), Error> {
+        let net = &self.net;
+        let shape = (
+            net.width.unwrap_or(0),
+            net.height.unwrap_or(0),
+            net.channels.unwrap_or(0),
+        );
+        if shape.0 * shape.1 * shape.2 != 0 {
+            return Ok(shape);
+        }
+        match net.options.get_parsed("inputs")?.unwrap_or(0) {
+            0 => Err(Error::InvalidConfigError {
+                reason: "the [net] section has no input size".into(),
+            }),
+            inputs => Ok((1, 1, inputs)),
+        }
+    }
+
+    /// Compute the output shape tuple (width, height, channels) of each layer, as libdarknet does.
+    ///
+    /// Layers that libdarknet leaves without a shape, such as `[softmax]` and `[cost]`, keep the
+    /// shape of their input. Returns an [Err] for layer types whose shape is not modeled, such as
+    /// recurrent layers.
+    pub fn output_shapes(&self) -> Result<Vec<(usize, usize, usize)>, Error> {
+        let mut shapes: Vec<(usize, usize, usize)> = Vec::with_capacity(self.layers.len());
+        let mut input = self.input_shape()?;
+
+        for (index, layer) in self.layers.iter().enumerate() {
+            let output = layer.output_shape(index, input, &shapes)?;
+            shapes.push(output);
+            input = output;
+        }
+
+        Ok(shapes)
+    }
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut sections = read_sections(text)?.into_iter();
+
+        let net = match sections.next() {
+            Some(section) if section.layer_type == LayerType::Network => {
+                NetConfig::from_options(section.options)?
+            }
+            Some(section) => {
+                return Err(Error::InvalidNetSectionError {
+                    found: format!("[{}]", section.name),
+                })
+            }
+            None => return Err(Error::EmptyConfigError),
+        };
+
+        let layers = sections
+            .map(LayerConfig::from_section)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { net, layers })
+    }
+}
+
+impl Display for Config {
+    /// Write the config in darknet syntax.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.net)?;
+        self.layers
+            .iter()
+            .try_for_each(|layer| write!(f, "\n{}", layer))
+    }
+}
+
+/// The options of the `[net]` section.
+///
+/// Unset fields take the darknet defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetConfig {
+    pub batch: Option<usize>,
+    pub subdivisions: Option<usize>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub channels: Option<usize>,
+    pub max_batches: Option<usize>,
+    pub learning_rate: Option<f32>,
+    pub momentum: Option<f32>,
+    pub decay: Option<f32>,
+    pub burn_in: Option<usize>,
+    /// Options without a typed field, in file order.
+    pub options: Options,
+}
+
+impl Display for NetConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[net]")?;
+        write_option(f, "batch", &self.batch)?;
+        write_option(f, "subdivisions", &self.subdivisions)?;
+        write_option(f, "width", &self.width)?;
+        write_option(f, "height", &self.height)?;
+        write_option(f, "channels", &self.channels)?;
+        write_option(f, "max_batches", &self.max_batches)?;
+        write_option(f, "learning_rate", &self.learning_rate)?;
+        write_option(f, "momentum", &self.momentum)?;
+        write_option(f, "decay", &self.decay)?;
+        write_option(f, "burn_in", &self.burn_in)?;
+        write!(f, "{}", self.options)
+    }
+}
+
+impl NetConfig {
+    fn from_options(options: Vec<RawOption>) -> Result<Self, Error> {
+        let mut config = Self::default();
+        for option in options {
+            match option.key.as_str() {
+                "batch" => option.assign(&mut config.batch)?,
+                "subdivisions" => option.assign(&mut config.subdivisions)?,
+                "width" => option.assign(&mut config.width)?,
+                "height" => option.assign(&mut config.height)?,
+                "channels" => option.assign(&mut config.channels)?,
+                "max_batches" => option.assign(&mut config.max_batches)?,
+                "learning_rate" => option.assign(&mut config.learning_rate)?,
+                "momentum" => option.assign(&mut config.momentum)?,
+                "decay" => option.assign(&mut config.decay)?,
+                "burn_in" => option.assign(&mut config.burn_in)?,
+                _ => config.options.insert(option),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// A layer section of the config.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayerConfig {
+    Convolutional(ConvolutionalConfig),
+    Connected(ConnectedConfig),
+    Maxpool(MaxpoolConfig),
+    Route(RouteConfig),
+    Shortcut(ShortcutConfig),
+    Upsample(UpsampleConfig),
+    Yolo(YoloConfig),
+    GaussianYolo(YoloConfig),
+    Region(RegionConfig),
+    /// A section without typed options.
+    Other(OtherConfig),
+}
+
+impl LayerConfig {
+    /// Get the layer type of the section.
+    pub fn layer_type(&self) -> LayerType {
+        match self {
+            Self::Convolutional(_) => LayerType::Convolutional,
+            Self::Connected(_) => LayerType::Connected,
+            Self::Maxpool(_) => LayerType::Maxpool,
+            Self::Route(_) => LayerType::Route,
+            Self::Shortcut(_) => LayerType::Shortcut,
+            Self::Upsample(_) => LayerType::Upsample,
+            Self::Yolo(_) => LayerType::Yolo,
+            Self::GaussianYolo(_) => LayerType::GaussianYolo,
+            Self::Region(_) => LayerType::Region,
+            Self::Other(config) => config.layer_type,
+        }
+    }
+
+    /// Get the options without a typed field.
+    pub fn options(&self) -> &Options {
+        match self {
+            Self::Convolutional(config) => &config.options,
+            Self::Connected(config) => &config.options,
+            Self::Maxpool(config) => &config.options,
+            Self::Route(config) => &config.options,
+            Self::Shortcut(config) => &config.options,
+            Self::Upsample(config) => &config.options,
+            Self::Yolo(config) | Self::GaussianYolo(config) => &config.options,
+            Self::Region(config) => &config.options,
+            Self::Other(config) => &config.options,
+        }
+    }
+
+    /// Get the options without a typed field mutably.
+    pub fn options_mut(&mut self) -> &mut Options {
+        match self {
+            Self::Convolutional(config) => &mut config.options,
+            Self::Connected(config) => &mut config.options,
+            Self::Maxpool(config) => &mut config.options,
+            Self::Route(config) => &mut config.options,
+            Self::Shortcut(config) => &mut config.options,
+            Self::Upsample(config) => &mut config.options,
+            Self::Yolo(config) | Self::GaussianYolo(config) => &mut config.options,
+            Self::Region(config) => &mut config.options,
+            Self::Other(config) => &mut config.options,
+        }
+    }
+
+    /// Compute the output shape of the layer at \[index\] given its input shape and the
+    /// output shapes of the previous layers.
+    fn output_shape(
+        &self,
+        index: usize,
+        input: (usize, usize, usize),
+        prev_shapes: &[(usize, usize, usize)],
+    ) -> Result<(usize, usize, usize), Error> {
+        let (w, h, c) = input;
+        let options = self.options();
+        let invalid = |reason: String| Error::InvalidConfigError {
+            reason: format!("[{}] layer {}: {}", self.layer_type().name(), index, reason),
+        };
+        let resolve = |relative: isize| -> Result<(usize, usize, usize), Error> {
+            let from = if relative < 0 {
+                index as isize + relative
+            } else {
+                relative
+            };
+            usize::try_from(from)
+                .ok()
+                .and_then(|from| prev_shapes.get(from))
+                .cloned()
+                .ok_or_else(|| invalid(format!("invalid input layer {}", relative)))
+        };
+        // (input + padding - size) / stride + 1, rejecting windows larger than the input
+        let window = |input: usize, padding: usize, size: usize, stride: usize| {
+            (input + padding)
+                .checked_sub(size)
+                .filter(|_| stride > 0)
+                .map(|span| span / stride + 1)
+                .ok_or_else(|| invalid("the window does not fit in the input".into()))
+        };
+
+        let shape = match self {
+            Self::Convolutional(conv) => {
+                let size = conv.size.unwrap_or(1);
+                let stride = conv.stride.unwrap_or(1);
+                let stride_x = options.get_parsed("stride_x")?.unwrap_or(stride);
+                let stride_y = options.get_parsed("stride_y")?.unwrap_or(stride);
+                let padding = if conv.pad.unwrap_or(false) {
+                    size / 2
+                } else {
+                    conv.padding.unwrap_or(0)
+                };
+                (
+                    window(w, 2 * padding, size, stride_x)?,
+                    window(h, 2 * padding, size, stride_y)?,
+                    conv.filters.unwrap_or(1),
+                )
+            }
+            Self::Connected(connected) => (1, 1, connected.output.unwrap_or(1)),
+            Self::Maxpool(_)
+            | Self::Other(OtherConfig {
+                layer_type: LayerType::LocalAvgpool,
+                ..
+            }) => {
+                let (stride, size, padding) = match self {
+                    Self::Maxpool(maxpool) => (maxpool.stride, maxpool.size, maxpool.padding),
+                    _ => (
+                        options.get_parsed("stride")?,
+                        options.get_parsed("size")?,
+                        options.get_parsed("padding")?,
+                    ),
+                };
+                let stride = stride.unwrap_or(1);
+                let size = size.unwrap_or(stride);
+                let padding = padding.unwrap_or(size.saturating_sub(1));
+                let stride_x = options.get_parsed("stride_x")?.unwrap_or(stride);
+                let stride_y = options.get_parsed("stride_y")?.unwrap_or(stride);
+
+                if options.get_parsed("maxpool_depth")?.unwrap_or(0) != 0 {
+                    (w, h, options.get_parsed("out_channels")?.unwrap_or(1))
+                } else {
+                    (
+                        window(w, padding, size, stride_x)?,
+                        window(h, padding, size, stride_y)?,
+                        c,
+                    )
+                }
+            }
+            Self::Route(route) => {
+                let layers = route
+                    .layers
+                    .as_ref()
+                    .filter(|layers| !layers.is_empty())
+                    .ok_or_else(|| invalid("no input layers".into()))?;
+                let (out_w, out_h, first_c) = resolve(layers[0])?;
+                let mut out_c = first_c;
+                for &layer in &layers[1..] {
+                    let (next_w, next_h, next_c) = resolve(layer)?;
+                    if (next_w, next_h) != (out_w, out_h) {
+                        return Err(invalid("input layers have different sizes".into()));
+                    }
+                    out_c += next_c;
+                }
+                (out_w, out_h, out_c / route.groups.unwrap_or(1).max(1))
+            }
+            Self::Upsample(upsample) => match upsample.stride.unwrap_or(2) {
+                stride if stride < 0 => (w / -stride as usize, h / -stride as usize, c),
+                stride => (w * stride as usize, h * stride as usize, c),
+            },
+            Self::Other(other) => match other.layer_type {
+                LayerType::Avgpool => (1, 1, c),
+                LayerType::Crop => (
+                    options.get_parsed("crop_width")?.unwrap_or(1),
+                    options.get_parsed("crop_height")?.unwrap_or(1),
+                    c,
+                ),
+                LayerType::Reorg | LayerType::ReorgOld => {
+                    let stride: usize = options.get_parsed("stride")?.unwrap_or(1);
+                    if options.get_parsed("reverse")?.unwrap_or(0) != 0 {
+                        (w * stride, h * stride, c / (stride * stride).max(1))
+                    } else {
+                        (w / stride.max(1), h / stride.max(1), c * stride * stride)
+                    }
+                }
+                LayerType::Implicit => (
+                    1,
+                    options.get_parsed("atoms")?.unwrap_or(1),
+                    options.get_parsed("filters")?.unwrap_or(128),
+                ),
+                LayerType::ScaleChannels | LayerType::Sam => {
+                    let from = options
+                        .get_parsed("from")?
+                        .ok_or_else(|| invalid("no input layer".into()))?;
+                    resolve(from)?
+                }
+                LayerType::Softmax
+                | LayerType::Dropout
+                | LayerType::Cost
+                | LayerType::Active
+                | LayerType::Normalization
+                | LayerType::Batchnorm
+                | LayerType::Detection
+                | LayerType::Empty
+                | LayerType::Contrastive => input,
+                layer_type => {
+                    return Err(invalid(format!(
+                        "shapes of [{}] layers are not supported",
+                        layer_type.name()
+                    )))
+                }
+            },
+            Self::Shortcut(_) | Self::Yolo(_) | Self::GaussianYolo(_) | Self::Region(_) => input,
+        };
+        Ok(shape)
+    }
+
+    fn from_section(section: RawSection) -> Result<Self, Error> {
+        let RawSection {
+            layer_type,
+            line,
+            options,
+            ..
+        } = section;
+
+        let config = match layer_type {
+            LayerType::Convolutional => {
+                Self::Convolutional(ConvolutionalConfig::from_options(options)?)
+            }
+            LayerType::Connected => Self::Connected(ConnectedConfig::from_options(options)?),
+            LayerType::Maxpool => Self::Maxpool(MaxpoolConfig::from_options(options)?),
+            LayerType::Route => Self::Route(RouteConfig::from_options(options)?),
+            LayerType::Shortcut => Self::Shortcut(ShortcutConfig::from_options(options)?),
+            LayerType::Upsample => Self::Upsample(UpsampleConfig::from_options(options)?),
+            LayerType::Yolo => Self::Yolo(YoloConfig::from_options(options)?),
+            LayerType::GaussianYolo => Self::GaussianYolo(YoloConfig::from_options(options)?),
+            LayerType::Region => Self::Region(RegionConfig::from_options(options)?),
+            LayerType::Network => {
+                return Err(Error::ConfigParseError {
+                    line,
+                    reason: "only the first section can be [net]".into(),
+                })
+            }
+            layer_type => {
+                let mut config = OtherConfig {
+                    layer_type,
+                    options: Options::default(),
+                };
+                options
+                    .into_iter()
+                    .for_each(|option| config.options.insert(option));
+                Self::Other(config)
+            }
+        };
+        Ok(config)
+    }
+}
+
+impl Display for LayerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Convolutional(config) => config.fmt(f),
+            Self::Connected(config) => config.fmt(f),
+            Self::Maxpool(config) => config.fmt(f),
+            Self::Route(config) => config.fmt(f),
+            Self::Shortcut(config) => config.fmt(f),
+            Self::Upsample(config) => config.fmt(f),
+            Self::Yolo(config) | Self::GaussianYolo(config) => {
+                writeln!(f, "[{}]", self.layer_type().name())?;
+                config.fmt_options(f)
+            }
+            Self::Region(config) => config.fmt(f),
+            Self::Other(config) => config.fmt(f),
+        }
+    }
+}
+
+/// The options of a `[convolutional]` section.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConvolutionalConfig {
+    pub filters: Option<usize>,
+    pub size: Option<usize>,
+    pub stride: Option<usize>,
+    /// Pad by `size / 2`, overriding \[padding\].
+    pub pad: Option<bool>,
+    pub padding: Option<usize>,
+    pub groups: Option<usize>,
+    pub dilation: Option<usize>,
+    pub batch_normalize: Option<bool>,
+    pub activation: Option<Activation>,
+    /// Options without a typed field, in file order.
+    pub options: Options,
+}
+
+impl Display for ConvolutionalConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[convolutional]")?;
+        write_option(f, "batch_normalize", &self.batch_normalize)?;
+        write_option(f, "filters", &self.filters)?;
+        write_option(f, "size", &self.size)?;
+        write_option(f, "stride", &self.stride)?;
+        write_option(f, "pad", &self.pad)?;
+        write_option(f, "padding", &self.padding)?;
+        write_option(f, "groups", &self.groups)?;
+        write_option(f, "dilation", &self.dilation)?;
+        write_option(f, "activation", &self.activation)?;
+        write!(f, "{}", self.options)
+    }
+}
+
+impl ConvolutionalConfig {
+    fn from_options(options: Vec<RawOption>) -> Result<Self, Error> {
+        let mut config = Self::default();
+        for option in options {
+            match option.key.as_str() {
+                "filters" => option.assign(&mut config.filters)?,
+                "size" => option.assign(&mut config.size)?,
+                "stride" => option.assign(&mut config.stride)?,
+                "pad" => option.assign_flag(&mut config.pad)?,
+                "padding" => option.assign(&mut config.padding)?,
+                "groups" => option.assign(&mut config.groups)?,
+                "dilation" => option.assign(&mut config.dilation)?,
+                "batch_normalize" => option.assign_flag(&mut config.batch_normalize)?,
+                "activation" => option.assign(&mut config.activation)?,
+                _ => config.options.insert(option),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The options of a `[connected]` section.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectedConfig {
+    pub output: Option<usize>,
+    pub batch_normalize: Option<bool>,
+    pub activation: Option<Activation>,
+    /// Options without a typed field, in file order.
+    pub options: Options,
+}
+
+impl Display for ConnectedConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[connected]")?;
+        write_option(f, "output", &self.output)?;
+        write_option(f, "batch_normalize", &self.batch_normalize)?;
+        write_option(f, "activation", &self.activation)?;
+        write!(f, "{}", self.options)
+    }
+}
+
+impl ConnectedConfig {
+    fn from_options(options: Vec<RawOption>) -> Result<Self, Error> {
+        let mut config = Self::default();
+        for option in options {
+            match option.key.as_str() {
+                "output" => option.assign(&mut config.output)?,
+                "batch_normalize" => option.assign_flag(&mut config.batch_normalize)?,
+                "activation" => option.assign(&mut config.activation)?,
+                _ => config.options.insert(option),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The options of a `[maxpool]` section.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaxpoolConfig {
+    pub size: Option<usize>,
+    pub stride: Option<usize>,
+    pub padding: Option<usize>,
+    /// Options without a typed field, in file order.
+    pub options: Options,
+}
+
+impl Display for MaxpoolConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[maxpool]")?;
+        write_option(f, "size", &self.size)?;
+        write_option(f, "stride", &self.stride)?;
+        write_option(f, "padding", &self.padding)?;
+        write!(f, "{}", self.options)
+    }
+}
+
+impl MaxpoolConfig {
+    fn from_options(options: Vec<RawOption>) -> Result<Self, Error> {
+        let mut config = Self::default();
+        for option in options {
+            match option.key.as_str() {
+                "size" => option.assign(&mut config.size)?,
+                "stride" => option.assign(&mut config.stride)?,
+                "padding" => option.assign(&mut config.padding)?,
+                _ => config.options.insert(option),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The options of a `[route]` section.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RouteConfig {
+    /// Input layer indexes. Negative indexes are relative to the route layer.
+    pub layers: Option<Vec<isize>>,
+    pub groups: Option<usize>,
+    pub group_id: Option<usize>,
+    /// Options without a typed field, in file order.
+    pub options: Options,
+}
+
+impl Display for RouteConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[route]")?;
+        write_option(f, "layers", &self.layers)?;
+        write_option(f, "groups", &self.groups)?;
+        write_option(f, "group_id", &self.group_id)?;
+        write!(f, "{}", self.options)
+    }
+}
+
+impl RouteConfig {
+    fn from_options(options: Vec<RawOption>) -> Result<Self, Error> {
+        let mut config = Self::default();
+        for option in options {
+            match option.key.as_str() {
+                "layers" => option.assign_list(&mut config.layers)?,
+                "groups" => option.assign(&mut config.groups)?,
+                "group_id" => option.assign(&mut config.group_id)?,
+                _ => config.options.insert(option),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The options of a `[shortcut]` section.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShortcutConfig {
+    /// Input layer indexes. Negative indexes are relative to the shortcut layer.
+    pub from: Option<Vec<isize>>,
+    pub activation: Option<Activation>,
+    /// Options without a typed field, in file order.
+    pub options: Options,
+}
+
+impl Display for ShortcutConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[shortcut]")?;
+        write_option(f, "from", &self.from)?;
+        write_option(f, "activation", &self.activation)?;
+        write!(f, "{}", self.options)
+    }
+}
+
+impl ShortcutConfig {
+    fn from_options(options: Vec<RawOption>) -> Result<Self, Error> {
+        let mut config = Self::default();
+        for option in options {
+            match option.key.as_str() {
+                "from" => option.assign_list(&mut config.from)?,
+                "activation" => option.assign(&mut config.activation)?,
+                _ => config.options.insert(option),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The options of an `[upsample]` section.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpsampleConfig {
+    /// The upsampling factor. A negative stride downsamples.
+    pub stride: Option<isize>,
+    pub scale: Option<f32>,
+    /// Options without a typed field, in file order.
+    pub options: Options,
+}
+
+impl Display for UpsampleConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[upsample]")?;
+        write_option(f, "stride", &self.stride)?;
+        write_option(f, "scale", &self.scale)?;
+        write!(f, "{}", self.options)
+    }
+}
+
+impl UpsampleConfig {
+    fn from_options(options: Vec<RawOption>) -> Result<Self, Error> {
+        let mut config = Self::default();
+        for option in options {
+            match option.key.as_str() {
+                "stride" => option.assign(&mut config.stride)?,
+                "scale" => option.assign(&mut config.scale)?,
+                _ => config.options.insert(option),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The options of a `[yolo]` or `[Gaussian_yolo]` section.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct YoloConfig {
+    pub classes: Option<usize>,
+    /// The total number of anchors.
+    pub num: Option<usize>,
+    /// Indexes of the anchors used by this layer.
+    pub mask: Option<Vec<usize>>,
+    /// Anchor (width, height) pairs in pixels.
+    pub anchors: Option<Vec<(f32, f32)>>,
+    pub jitter: Option<f32>,
+    pub ignore_thresh: Option<f32>,
+    pub truth_thresh: Option<f32>,
+    pub scale_x_y: Option<f32>,
+    pub iou_loss: Option<IoULoss>,
+    pub nms_kind: Option<NmsKind>,
+    pub beta_nms: Option<f32>,
+    /// Options without a typed field, in file order.
+    pub options: Options,
+}
+
+impl YoloConfig {
+    /// Set the number of classes and return the filters expected from the previous layer.
+    ///
+    /// Each anchor predicts \[box_params\] values besides the class probabilities.
+    fn retarget_classes(
+        &mut self,
+        classes: usize,
+        box_params: usize,
+        index: usize,
+    ) -> Result<usize, Error> {
+        let num = self.num.unwrap_or(1);
+        let num_anchors = self.anchors.as_ref().map_or(num, Vec::len);
+        let num_masks = match &self.mask {
+            Some(mask) => {
+                if let Some(anchor) = mask.iter().find(|&&anchor| anchor >= num.min(num_anchors)) {
+                    return Err(Error::InvalidConfigError {
+                        reason: format!(
+                            "mask index {} of layer {} is out of range for num={} and {} anchors",
+                            anchor, index, num, num_anchors
+                        ),
+                    });
+                }
+                mask.len()
+            }
+            None => num,
+        };
+
+        self.classes = Some(classes);
+        self.options.remove("counters_per_class");
+        Ok((classes + box_params) * num_masks)
+    }
+
+    fn fmt_options(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_option(f, "mask", &self.mask)?;
+        write_option(f, "anchors", &self.anchors)?;
+        write_option(f, "classes", &self.classes)?;
+        write_option(f, "num", &self.num)?;
+        write_option(f, "jitter", &self.jitter)?;
+        write_option(f, "ignore_thresh", &self.ignore_thresh)?;
+        write_option(f, "truth_thresh", &self.truth_thresh)?;
+        write_option(f, "scale_x_y", &self.scale_x_y)?;
+        write_option(f, "iou_loss", &self.iou_loss)?;
+        write_option(f, "nms_kind", &self.nms_kind)?;
+        write_option(f, "beta_nms", &self.beta_nms)?;
+        write!(f, "{}", self.options)
+    }
+
+    fn from_options(options: Vec<RawOption>) -> Result<Self, Error> {
+        let mut config = Self::default();
+        for option in options {
+            match option.key.as_str() {
+                "classes" => option.assign(&mut config.classes)?,
+                "num" => option.assign(&mut config.num)?,
+                "mask" => option.assign_list(&mut config.mask)?,
+                "anchors" => option.assign_pairs(&mut config.anchors)?,
+                "jitter" => option.assign(&mut config.jitter)?,
+                "ignore_thresh" => option.assign(&mut config.ignore_thresh)?,
+                "truth_thresh" => option.assign(&mut config.truth_thresh)?,
+                "scale_x_y" => option.assign(&mut config.scale_x_y)?,
+                "iou_loss" => option.assign(&mut config.iou_loss)?,
+                "nms_kind" => option.assign(&mut config.nms_kind)?,
+                "beta_nms" => option.assign(&mut config.beta_nms)?,
+                _ => config.options.insert(option),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The options of a `[region]` section.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegionConfig {
+    pub classes: Option<usize>,
+    pub num: Option<usize>,
+    pub coords: Option<usize>,
+    /// Anchor (width, height) pairs in grid cells.
+    pub anchors: Option<Vec<(f32, f32)>>,
+    /// Options without a typed field, in file order.
+    pub options: Options,
+}
+
+impl Display for RegionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[region]")?;
+        write_option(f, "anchors", &self.anchors)?;
+        write_option(f, "classes", &self.classes)?;
+        write_option(f, "coords", &self.coords)?;
+        write_option(f, "num", &self.num)?;
+        write!(f, "{}", self.options)
+    }
+}
+
+impl RegionConfig {
+    fn from_options(options: Vec<RawOption>) -> Result<Self, Error> {
+        let mut config = Self::default();
+        for option in options {
+            match option.key.as_str() {
+                "classes" => option.assign(&mut config.classes)?,
+                "num" => option.assign(&mut config.num)?,
+                "coords" => option.assign(&mut config.coords)?,
+                "anchors" => option.assign_pairs(&mut config.anchors)?,
+                _ => config.options.insert(option),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// A section without typed options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtherConfig {
+    pub layer_type: LayerType,
+    /// All options of the section, in file order.
+    pub options: Options,
+}
+
+impl Display for OtherConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[{}]", self.layer_type.name())?;
+        write!(f, "{}", self.options)
+    }
+}
+
+/// Untyped `key=value` options in file order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    entries: Vec<(String, String)>,
+}
+
+impl Options {
+    /// Get the value of an option.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set the value of an option, appending it if it is not present.
+    pub fn set<V: ToString>(&mut self, key: &str, value: V) {
+        let value = value.to_string();
+        match self.entries.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((key.to_owned(), value)),
+        }
+    }
+
+    /// Remove an option and return its value.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    /// Get the iterator of (key, value) pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Return options count.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no options.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Parse the value of an untyped option.
+    pub(crate) fn get_parsed<T: FromStr>(&self, key: &str) -> Result<Option<T>, Error> {
+        self.get(key)
+            .map(|value| {
+                value.parse().map_err(|_| Error::InvalidConfigError {
+                    reason: format!("invalid value {:?} for option {:?}", value, key),
+                })
+            })
+            .transpose()
+    }
+
+    fn insert(&mut self, option: RawOption) {
+        if self.get(&option.key).is_none() {
+            self.entries.push((option.key, option.value));
+        }
+    }
+}
+
+impl Display for Options {
+    /// Write one `key=value` line per option.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.entries
+            .iter()
+            .try_for_each(|(key, value)| writeln!(f, "{}={}", key, value))
+    }
+}
+
+/// A typed option value that can be written in darknet syntax.
+trait CfgValue {
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+macro_rules! impl_cfg_value_display {
+    ($($type_:ty),*) => {
+        $(
+            impl CfgValue for $type_ {
+                fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write!(f, "{}", self)
+                }
+            }
+        )*
+    };
+}
+
+impl_cfg_value_display!(usize, isize, f32);
+
+impl CfgValue for bool {
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", *self as u8)
+    }
+}
+
+impl CfgValue for Activation {
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl CfgValue for IoULoss {
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl CfgValue for NmsKind {
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl CfgValue for (f32, f32) {
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.0, self.1)
+    }
+}
+
+impl<T: CfgValue> CfgValue for Vec<T> {
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.iter().enumerate().try_for_each(|(index, value)| {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            value.fmt_value(f)
+        })
+    }
+}
+
+/// Write a `key=value` line if the value is set.
+fn write_option<T: CfgValue>(
+    f: &mut fmt::Formatter<'_>,
+    key: &str,
+    value: &Option<T>,
+) -> fmt::Result {
+    match value {
+        Some(value) => {
+            write!(f, "{}=", key)?;
+            value.fmt_value(f)?;
+            writeln!(f)
+        }
+        None => Ok(()),
+    }
+}
+
+/// A section as written in the file, before its options are typed.
+struct RawSection {
+    name: String,
+    layer_type: LayerType,
+    line: usize,
+    options: Vec<RawOption>,
+}
+
+/// A `key=value` line with its 1-based line number.
+struct RawOption {
+    key: String,
+    value: String,
+    line: usize,
+}
+
+impl RawOption {
+    fn error(&self, reason: &str) -> Error {
+        Error::ConfigParseError {
+            line: self.line,
+            reason: format!("{} {:?}", reason, self.key),
+        }
+    }
+
+    fn parse<T: FromStr>(&self, value: &str) -> Result<T, Error> {
+        value.parse().map_err(|_| Error::ConfigParseError {
+            line: self.line,
+            reason: format!("invalid value {:?} for option {:?}", value, self.key),
+        })
+    }
+
+    /// Assign a typed value. Like darknet, the first occurrence of a repeated option wins.
+    fn assign<T: FromStr>(&self, field: &mut Option<T>) -> Result<(), Error> {
+        if field.is_none() {
+            *field = Some(self.parse(&self.value)?);
+        }
+        Ok(())
+    }
+
+    /// Assign a 0/1 flag. Like darknet, any non-zero integer is true.
+    fn assign_flag(&self, field: &mut Option<bool>) -> Result<(), Error> {
+        if field.is_none() {
+            *field = Some(self.parse::<i32>(&self.value)? != 0);
+        }
+        Ok(())
+    }
+
+    fn assign_list<T: FromStr>(&self, field: &mut Option<Vec<T>>) -> Result<(), Error> {
+        if field.is_some() {
+            return Ok(());
+        }
+        let list = self
+            .value
+            .split(',')
+            .map(|item| self.parse(item))
+            .collect::<Result<_, _>>()?;
+        *field = Some(list);
+        Ok(())
+    }
+
+    fn assign_pairs(&self, field: &mut Option<Vec<(f32, f32)>>) -> Result<(), Error> {
+        if field.is_some() {
+            return Ok(());
+        }
+        let list = self
+            .value
+            .split(',')
+            .map(|item| self.parse::<f32>(item))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !list.len().is_multiple_of(2) {
+            return Err(self.error("expect (width, height) pairs for option"));
+        }
+        *field = Some(list.chunks(2).map(|pair| (pair[0], pair[1])).collect());
+        Ok(())
+    }
+}
+
+/// Split the config text into sections the way libdarknet reads it.
+fn read_sections(text: &str) -> Result<Vec<RawSection>, Error> {
+    let mut sections: Vec<RawSection> = vec![];
+
+    for (index, line) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = strip(line);
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            let name = line
+                .strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+                .ok_or_else(|| Error::ConfigParseError {
+                    line: line_no,
+                    reason: format!("malformed section header {:?}", line),
+                })?;
+            let layer_type = name.parse().map_err(|_| Error::ConfigParseError {
+                line: line_no,
+                reason: format!("unknown section [{}]", name),
+            })?;
+            sections.push(RawSection {
+                name: name.to_owned(),
+                layer_type,
+                line: line_no,
+                options: vec![],
+            });
+            continue;
+        }
+
+        let section = sections.last_mut().ok_or_else(|| Error::ConfigParseError {
+            line: line_no,
+            reason: "option outside of a section".into(),
+        })?;
+
+        // values may carry trailing comments, e.g. "layers = 180 ### P6"
+        let option = line
+            .split_once('=')
+            .map(|(key, value)| (key, value.split('#').next().unwrap()))
+            .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+            .ok_or_else(|| Error::ConfigParseError {
+                line: line_no,
+                reason: format!("expect key=value, but found {:?}", line),
+            })?;
+
+        section.options.push(RawOption {
+            key: option.0.to_owned(),
+            value: option.1.to_owned(),
+            line: line_no,
+        });
+    }
+
+    Ok(sections)
+}
+
+/// Check that a config file can be read and that it starts with a `[net]` section, and get the
+/// [Config] if it parses. See [check_str].
+pub(crate) fn check_file(path: &Path) -> Result<Option<Config>, Error> {
+    let bytes = fs::read(path).map_err(|error| Error::ConfigOpenError {
+        path: path.to_owned(),
+        error,
+    })?;
+    check_str(&String::from_utf8_lossy(&bytes))
+}
+
+/// Check that the config text starts with a `[net]` section, the way libdarknet reads it, and
+/// get the [Config] if it parses.
+///
+/// libdarknet reads the layer sections more loosely than [Config], so only the `[net]` header
+/// is checked if the text does not parse.
+pub(crate) fn check_str(text: &str) -> Result<Option<Config>, Error> {
+    match text.parse() {
+        Ok(config) => Ok(Some(config)),
+        Err(_) => check_header(text).map(|()| None),
+    }
+}
+
+fn check_header(text: &str) -> Result<(), Error> {
+    let first = text
+        .lines()
+        .map(strip)
+        .find(|line| !(line.is_empty() || line.starts_with('#') || line.starts_with(';')));
+
+    match first {
+        None => Err(Error::EmptyConfigError),
+        Some(line) if line == "[net]" || line == "[network]" => Ok(()),
+        Some(line) => Err(Error::InvalidNetSectionError { found: line }),
+    }
+}
+
+/// Remove all whitespaces from the line, as libdarknet does before parsing it.
+fn strip(line: &str) -> String {
+    line.chars().filter(|c| !c.is_whitespace()).collect()
+}
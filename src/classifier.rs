@@ -0,0 +1,134 @@
+use crate::{
+    error::Error,
+    image::{Image, IntoCowImage},
+    labels::Labels,
+    network::Network,
+};
+use darknet_sys as sys;
+use std::{cmp::Ordering, os::raw::c_int, path::Path};
+
+/// A class predicted by a [Classifier].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Classification {
+    /// The class index.
+    pub index: usize,
+    /// The label name of the class.
+    pub label: String,
+    /// The output probability of the class.
+    pub probability: f32,
+}
+
+/// The wrapper of a classification network, such as darknet53 or csdarknet53.
+pub struct Classifier {
+    network: Network,
+    labels: Vec<String>,
+}
+
+impl Classifier {
+    /// Wrap a network with one label per output.
+    ///
+    /// It returns an [Err] if the number of labels is not the output size of the network.
+    pub fn new(network: Network, labels: Vec<String>) -> Result<Self, Error> {
+        let classes = network.output_layer().num_outputs();
+        if labels.len() != classes {
+            return Err(Error::LabelCountError {
+                labels: labels.len(),
+                classes,
+            });
+        }
+        Ok(Self { network, labels })
+    }
+
+    /// Load the network from config and weights files, and the labels from a `.names` file
+    /// with one label per line.
+    pub fn load<C, W, L>(cfg: C, weights: W, label_file: L) -> Result<Self, Error>
+    where
+        C: AsRef<Path>,
+        W: AsRef<Path>,
+        L: AsRef<Path>,
+    {
+        let labels = Labels::open(label_file)?.into_vec();
+        let network = Network::load(cfg, Some(weights), false)?;
+        Self::new(network, labels)
+    }
+
+    /// Get the wrapped network.
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+
+    /// Get the wrapped network mutably.
+    pub fn network_mut(&mut self) -> &mut Network {
+        &mut self.network
+    }
+
+    /// Unwrap the network.
+    pub fn into_network(self) -> Network {
+        self.network
+    }
+
+    /// Get the label names.
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// Run the network on an image and return the probabilities of all classes.
+    ///
+    /// The image is preprocessed like `darknet classifier predict`: it is resized so that its
+    /// shorter side matches the network input, then center-cropped to the input size.
+    pub fn probabilities<'a, M>(&mut self, image: M) -> Result<Vec<f32>, Error>
+    where
+        M: IntoCowImage<'a>,
+    {
+        let input = self.preprocess(&image.into_cow_image());
+        Ok(self.network.forward(input.get_data())?.to_vec())
+    }
+
+    /// Run the network on an image and return the \[top_k\] most probable classes, in descending
+    /// order of probability.
+    pub fn classify<'a, M>(&mut self, image: M, top_k: usize) -> Result<Vec<Classification>, Error>
+    where
+        M: IntoCowImage<'a>,
+    {
+        let probabilities = self.probabilities(image)?;
+
+        let mut indexes: Vec<usize> = (0..probabilities.len()).collect();
+        // stable sort, so ties keep the lower class index first
+        indexes.sort_by(|&a, &b| {
+            probabilities[b]
+                .partial_cmp(&probabilities[a])
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let classifications = indexes
+            .into_iter()
+            .take(top_k)
+            .map(|index| Classification {
+                index,
+                label: self.labels[index].clone(),
+                probability: probabilities[index],
+            })
+            .collect();
+        Ok(classifications)
+    }
+
+    fn preprocess(&self, image: &Image) -> Image {
+        let width = self.network.input_width();
+        let height = self.network.input_height();
+        unsafe {
+            let resized = Image::from_raw(sys::resize_min(*image.as_raw(), width as c_int));
+            let (dx, dy) = (
+                (resized.width() as c_int - width as c_int) / 2,
+                (resized.height() as c_int - height as c_int) / 2,
+            );
+            Image::from_raw(sys::crop_image(
+                *resized.as_raw(),
+                dx,
+                dy,
+                width as c_int,
+                height as c_int,
+            ))
+        }
+    }
+}
@@ -1,11 +1,11 @@
-use crate::BBox;
+use crate::{bbox::BoundingBox, labels::Labels, letterbox::LetterBox, BBox};
 use darknet_sys as sys;
 use std::cmp::Ordering::Less;
 use std::{
     iter::{ExactSizeIterator, FusedIterator, Iterator},
     os::raw::c_int,
     ptr::NonNull,
-    slice,
+    slice, vec,
 };
 
 /// An instance of detection.
@@ -20,6 +20,11 @@ impl<'a> Detection<'a> {
         &self.detection.bbox
     }
 
+    /// Get the bounding box of the object as a [BoundingBox].
+    pub fn bounding_box(&self) -> BoundingBox {
+        self.detection.bbox.into()
+    }
+
     /// Get the number of classes.
     pub fn num_classes(&self) -> usize {
         self.detection.classes as usize
@@ -37,12 +42,19 @@ impl<'a> Detection<'a> {
     /// it returns the tuple (class_id, corresponding_probability).
     /// Otherwise, it returns None.
     pub fn best_class(&self, prob_threshold: Option<f32>) -> Option<(usize, f32)> {
-        self.probabilities()
-            .iter()
-            .enumerate()
-            .filter(|(_, prob)| prob_threshold.map_or(true, |thresh| thresh.lt(prob)))
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Less))
-            .map(|(idx, prob)| (idx, *prob))
+        best_class(self.probabilities(), prob_threshold)
+    }
+
+    /// Get the label of the class with maximum probability, like [Detection::best_class].
+    ///
+    /// It returns None if the class has no label.
+    pub fn best_class_label<'l>(
+        &self,
+        labels: &'l Labels,
+        prob_threshold: Option<f32>,
+    ) -> Option<(&'l str, f32)> {
+        let (class, prob) = self.best_class(prob_threshold)?;
+        Some((labels.get(class)?, prob))
     }
 
     pub fn uc(&self) -> Option<&[f32]> {
@@ -64,11 +76,112 @@ impl<'a> Detection<'a> {
     }
 }
 
+/// A detection that owns its data, independent of the [Detections] it is copied from.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedDetection {
+    /// The bounding box of the object.
+    #[cfg_attr(feature = "serde", serde(with = "crate::BBoxDef"))]
+    pub bbox: BBox,
+    /// The score of objectness.
+    pub objectness: f32,
+    /// The output probabilities of each class.
+    pub probabilities: Vec<f32>,
+    /// The uncertainties of the box coordinates from a `[Gaussian_yolo]` layer.
+    pub uc: Option<[f32; 4]>,
+    /// The class index the detection was sorted by in NMS.
+    pub sort_class: usize,
+}
+
+impl OwnedDetection {
+    /// Create a detection of a box with the probabilities of each class, e.g. from another model.
+    ///
+    /// The objectness is the best class probability, and the detection is sorted by that class.
+    pub fn new<B>(bbox: B, probabilities: Vec<f32>) -> Self
+    where
+        B: Into<BBox>,
+    {
+        let (sort_class, objectness) = best_class(&probabilities, None).unwrap_or((0, 0.0));
+        Self {
+            bbox: bbox.into(),
+            objectness,
+            probabilities,
+            uc: None,
+            sort_class,
+        }
+    }
+
+    /// Get the bounding box of the object as a [BoundingBox].
+    pub fn bounding_box(&self) -> BoundingBox {
+        self.bbox.into()
+    }
+
+    /// Map the box from the letterboxed network input to the image, both normalized.
+    pub fn unletter_box(&mut self, transform: &LetterBox) {
+        self.bbox = transform.to_image(&self.bbox.into()).into();
+    }
+
+    /// Map the box from the image to the letterboxed network input, both normalized.
+    pub fn letter_box(&mut self, transform: &LetterBox) {
+        self.bbox = transform.to_network(&self.bbox.into()).into();
+    }
+
+    /// Get the number of classes.
+    pub fn num_classes(&self) -> usize {
+        self.probabilities.len()
+    }
+
+    /// Get the class index with maximum probability.
+    ///
+    /// See [Detection::best_class] for details.
+    pub fn best_class(&self, prob_threshold: Option<f32>) -> Option<(usize, f32)> {
+        best_class(&self.probabilities, prob_threshold)
+    }
+
+    /// Get the label of the class with maximum probability.
+    ///
+    /// See [Detection::best_class_label] for details.
+    pub fn best_class_label<'l>(
+        &self,
+        labels: &'l Labels,
+        prob_threshold: Option<f32>,
+    ) -> Option<(&'l str, f32)> {
+        let (class, prob) = self.best_class(prob_threshold)?;
+        Some((labels.get(class)?, prob))
+    }
+}
+
+impl<'a> From<Detection<'a>> for OwnedDetection {
+    fn from(detection: Detection<'a>) -> Self {
+        Self {
+            bbox: *detection.bbox(),
+            objectness: detection.objectness(),
+            probabilities: detection.probabilities().to_vec(),
+            uc: detection.uc().map(|uc| [uc[0], uc[1], uc[2], uc[3]]),
+            sort_class: detection.sort_class(),
+        }
+    }
+}
+
+fn best_class(probabilities: &[f32], prob_threshold: Option<f32>) -> Option<(usize, f32)> {
+    probabilities
+        .iter()
+        .enumerate()
+        .filter(|(_, prob)| match prob_threshold {
+            Some(thresh) => thresh.lt(prob),
+            None => true,
+        })
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Less))
+        .map(|(idx, prob)| (idx, *prob))
+}
+
 /// A collection of detections.
 #[derive(Debug)]
 pub struct Detections {
     pub(crate) detections: NonNull<sys::detection>,
     pub(crate) n_detections: usize,
+    /// The length of the array, which may hold filtered out detections past \[n_detections\].
+    pub(crate) n_allocated: usize,
 }
 
 impl Detections {
@@ -102,6 +215,21 @@ impl Detections {
             index: 0,
         }
     }
+
+    /// Copy the detections into owned values.
+    pub fn to_owned_vec(&self) -> Vec<OwnedDetection> {
+        self.iter().map(OwnedDetection::from).collect()
+    }
+}
+
+impl IntoIterator for Detections {
+    type Item = OwnedDetection;
+    type IntoIter = vec::IntoIter<OwnedDetection>;
+
+    /// Copy the detections into owned values and free the detection array.
+    fn into_iter(self) -> Self::IntoIter {
+        self.to_owned_vec().into_iter()
+    }
 }
 
 impl<'a> IntoIterator for &'a Detections {
@@ -116,7 +244,7 @@ impl<'a> IntoIterator for &'a Detections {
 impl Drop for Detections {
     fn drop(&mut self) {
         unsafe {
-            sys::free_detections(self.detections.as_mut(), self.n_detections as c_int);
+            sys::free_detections(self.detections.as_mut(), self.n_allocated as c_int);
         }
     }
 }
@@ -0,0 +1,339 @@
+use crate::{detections::Detections, image::Image, labels::Labels, BBox};
+use std::{borrow::Borrow, sync::OnceLock};
+
+/// The colors that classes are interpolated between, like darknet.
+const COLORS: [[f32; 3]; 6] = [
+    [1.0, 0.0, 1.0],
+    [0.0, 0.0, 1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, 1.0, 0.0],
+    [1.0, 1.0, 0.0],
+    [1.0, 0.0, 0.0],
+];
+
+/// The opacity of labels.
+const LABEL_ALPHA: f32 = 0.7;
+
+/// The number of glyph sizes.
+const GLYPH_SIZES: usize = 8;
+
+/// The first and last printable ASCII characters that have glyphs.
+const FIRST_GLYPH: char = ' ';
+const LAST_GLYPH: char = '~';
+
+macro_rules! glyph_files {
+    ($($code:literal)*) => {
+        [$(glyph_files!(@sizes $code)),*]
+    };
+    (@sizes $code:literal) => {
+        [
+            glyph_files!(@file $code 0),
+            glyph_files!(@file $code 1),
+            glyph_files!(@file $code 2),
+            glyph_files!(@file $code 3),
+            glyph_files!(@file $code 4),
+            glyph_files!(@file $code 5),
+            glyph_files!(@file $code 6),
+            glyph_files!(@file $code 7),
+        ]
+    };
+    (@file $code:literal $size:literal) => {
+        include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/data/labels/",
+            $code,
+            "_",
+            $size,
+            ".png"
+        ))
+    };
+}
+
+/// The PNG files of the glyphs in `data/labels`, indexed by character and size.
+static GLYPH_FILES: [[&[u8]; GLYPH_SIZES]; 95] = glyph_files!(
+    32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47
+    48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63
+    64 65 66 67 68 69 70 71 72 73 74 75 76 77 78 79
+    80 81 82 83 84 85 86 87 88 89 90 91 92 93 94 95
+    96 97 98 99 100 101 102 103 104 105 106 107 108 109 110 111
+    112 113 114 115 116 117 118 119 120 121 122 123 124 125 126
+);
+
+/// The decoded glyphs, indexed by size and character.
+static GLYPHS: [OnceLock<Vec<Bitmap>>; GLYPH_SIZES] = [const { OnceLock::new() }; GLYPH_SIZES];
+
+/// Get the color of a class out of a number of classes, like darknet.
+///
+/// The color is an RGB triple with values in `0.0..=1.0`.
+pub fn class_color(class: usize, classes: usize) -> [f32; 3] {
+    let offset = class * 123457 % classes;
+    [
+        color_channel(2, offset, classes),
+        color_channel(1, offset, classes),
+        color_channel(0, offset, classes),
+    ]
+}
+
+fn color_channel(channel: usize, offset: usize, classes: usize) -> f32 {
+    let ratio = offset as f32 / classes as f32 * 5.0;
+    let (lower, upper) = (ratio.floor(), ratio.ceil());
+    let ratio = ratio - lower;
+    (1.0 - ratio) * COLORS[lower as usize][channel] + ratio * COLORS[upper as usize][channel]
+}
+
+impl Image {
+    /// Draw a bounding box, normalized by the image size, with lines of the given thickness.
+    ///
+    /// The lines are drawn inside the box. Only the first 3 channels are drawn.
+    pub fn draw_box<B>(&mut self, bbox: B, thickness: usize, rgb: [f32; 3])
+    where
+        B: Borrow<BBox>,
+    {
+        let [left, top, right, bottom] = self.box_corners(bbox.borrow());
+        for offset in 0..thickness as isize {
+            self.draw_rectangle(
+                left + offset,
+                top + offset,
+                right - offset,
+                bottom - offset,
+                rgb,
+            );
+        }
+    }
+
+    /// Draw a text label at the given column, with the glyph size scaled to the image height.
+    ///
+    /// The label is drawn above the `row` if it fits, or below it otherwise. Characters that
+    /// are not printable ASCII are drawn as `?`.
+    pub fn draw_label(&mut self, text: &str, column: usize, row: usize, rgb: [f32; 3]) {
+        let size = (self.height() as f64 * 0.02) as usize / 10;
+        let label = render_label(text, size);
+        let (channels, height, width) = self.shape();
+        let row = if row >= label.height {
+            row - label.height
+        } else {
+            row
+        };
+        let data = self.get_data_mut();
+
+        for y in 0..label.height.min(height.saturating_sub(row)) {
+            for x in 0..label.width.min(width.saturating_sub(column)) {
+                for (c, &color) in rgb.iter().enumerate().take(channels) {
+                    let index = c * height * width + (row + y) * width + column + x;
+                    data[index] = label.get(x, y, c) * color * LABEL_ALPHA
+                        + data[index] * (1.0 - LABEL_ALPHA);
+                }
+            }
+        }
+    }
+
+    /// Draw the boxes and labels of detections like `darknet detector test`.
+    ///
+    /// The boxes must be normalized by the image size, which is the default of
+    /// [Network::predict](crate::Network::predict). Each detection is drawn with the color of
+    /// its most probable class, and labeled with the class names and probability. Classes
+    /// with zero probabilities, or with labels starting with `dont_show`, are not drawn.
+    pub fn draw_detections(&mut self, detections: &Detections, labels: &Labels) {
+        let shown = |class: usize| {
+            !labels
+                .get(class)
+                .is_some_and(|label| label.starts_with("dont_show"))
+        };
+        let name = |class: usize| {
+            labels
+                .get(class)
+                .map_or_else(|| class.to_string(), ToOwned::to_owned)
+        };
+
+        let mut selected: Vec<_> = detections
+            .iter()
+            .filter_map(|detection| {
+                let (class, prob) = detection
+                    .probabilities()
+                    .iter()
+                    .enumerate()
+                    .filter(|&(class, &prob)| prob > 0.0 && shown(class))
+                    .fold(None, |best, (class, &prob)| match best {
+                        Some((_, best_prob)) if prob <= best_prob => best,
+                        _ => Some((class, prob)),
+                    })?;
+                Some((detection, class, prob))
+            })
+            .collect();
+        // the most probable detections are drawn last, on top of the others
+        selected.sort_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+
+        let thickness = ((self.height() as f64 * 0.002) as usize).max(1);
+        for (detection, class, prob) in selected {
+            let probabilities = detection.probabilities();
+            let rgb = class_color(class, probabilities.len());
+            let box_rgb = if self.channels() == 1 {
+                [if thickness % 2 == 1 { 0.8 } else { 0.2 }; 3]
+            } else {
+                rgb
+            };
+            self.draw_box(detection.bbox(), thickness, box_rgb);
+
+            let mut text = format!("{}: {:.2}", name(class), prob);
+            for (other, &other_prob) in probabilities.iter().enumerate() {
+                if other != class && other_prob > 0.0 {
+                    text.push_str(", ");
+                    text.push_str(&name(other));
+                }
+            }
+            let [left, top, _, _] = self.box_corners(detection.bbox());
+            self.draw_label(&text, left as usize, top as usize + thickness, rgb);
+        }
+    }
+
+    /// Get the pixel corners of a normalized box, where the left and top are not negative and
+    /// the right and bottom are inside the image.
+    fn box_corners(&self, bbox: &BBox) -> [isize; 4] {
+        let (width, height) = (self.width() as f64, self.height() as f64);
+        let BBox { x, y, w, h } = *bbox;
+        let (x, y, w, h) = (x as f64, y as f64, w as f64, h as f64);
+        let left = ((x - w / 2.0) * width) as isize;
+        let right = ((x + w / 2.0) * width) as isize;
+        let top = ((y - h / 2.0) * height) as isize;
+        let bottom = ((y + h / 2.0) * height) as isize;
+        [
+            left.max(0),
+            top.max(0),
+            right.min(self.width() as isize - 1),
+            bottom.min(self.height() as isize - 1),
+        ]
+    }
+
+    /// Draw a one pixel wide rectangle with the corners clamped to the image.
+    fn draw_rectangle(&mut self, x1: isize, y1: isize, x2: isize, y2: isize, rgb: [f32; 3]) {
+        let (channels, height, width) = self.shape();
+        if width == 0 || height == 0 {
+            return;
+        }
+        let clamp_x = |x: isize| x.clamp(0, width as isize - 1) as usize;
+        let clamp_y = |y: isize| y.clamp(0, height as isize - 1) as usize;
+        let (x1, x2, y1, y2) = (clamp_x(x1), clamp_x(x2), clamp_y(y1), clamp_y(y2));
+        let data = self.get_data_mut();
+
+        for (c, &color) in rgb.iter().enumerate().take(channels) {
+            let plane = &mut data[c * height * width..(c + 1) * height * width];
+            for x in x1..=x2 {
+                plane[y1 * width + x] = color;
+                plane[y2 * width + x] = color;
+            }
+            for y in y1..=y2 {
+                plane[y * width + x1] = color;
+                plane[y * width + x2] = color;
+            }
+        }
+    }
+}
+
+/// A planar RGB image of glyphs.
+#[derive(Debug, Clone)]
+struct Bitmap {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+}
+
+impl Bitmap {
+    fn filled(width: usize, height: usize, value: f32) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![value; 3 * width * height],
+        }
+    }
+
+    fn decode(png: &[u8]) -> Self {
+        let image = image::load_from_memory(png)
+            .expect("the bundled glyphs are valid PNG files")
+            .to_rgb8();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let mut bitmap = Self::filled(width, height, 0.0);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            for (c, &value) in pixel.0.iter().enumerate() {
+                bitmap.set(x as usize, y as usize, c, value as f32 / 255.0);
+            }
+        }
+        bitmap
+    }
+
+    fn get(&self, x: usize, y: usize, c: usize) -> f32 {
+        self.data[(c * self.height + y) * self.width + x]
+    }
+
+    fn set(&mut self, x: usize, y: usize, c: usize, value: f32) {
+        self.data[(c * self.height + y) * self.width + x] = value;
+    }
+
+    /// Append a glyph overlapping by `overlap` columns, where the pixels are multiplied.
+    fn tile(&self, glyph: &Bitmap, overlap: usize) -> Self {
+        let width = self.width + glyph.width - overlap;
+        let mut tiled = Self::filled(width, self.height.max(glyph.height), 1.0);
+        for c in 0..3 {
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    tiled.set(x, y, c, self.get(x, y, c));
+                }
+            }
+            for y in 0..glyph.height {
+                for x in 0..glyph.width {
+                    let (tx, ty) = (self.width - overlap + x, y);
+                    let value = tiled.get(tx, ty, c) * glyph.get(x, y, c);
+                    tiled.set(tx, ty, c, value);
+                }
+            }
+        }
+        tiled
+    }
+
+    /// Surround the bitmap with a white border.
+    fn border(&self, border: usize) -> Self {
+        let mut bordered = Self::filled(self.width + 2 * border, self.height + 2 * border, 1.0);
+        for c in 0..3 {
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    bordered.set(x + border, y + border, c, self.get(x, y, c));
+                }
+            }
+        }
+        bordered
+    }
+}
+
+/// Render a text with the glyphs of a size, like `get_label_v3` of darknet.
+fn render_label(text: &str, size: usize) -> Bitmap {
+    let size = size.min(GLYPH_SIZES - 1);
+    let glyphs = GLYPHS[size].get_or_init(|| {
+        GLYPH_FILES
+            .iter()
+            .map(|files| Bitmap::decode(files[size]))
+            .collect()
+    });
+    let overlap = size + 1 - size.div_ceil(2);
+
+    let label = text
+        .chars()
+        .map(|ch| {
+            let ch = if (FIRST_GLYPH..=LAST_GLYPH).contains(&ch) {
+                ch
+            } else {
+                '?'
+            };
+            &glyphs[ch as usize - FIRST_GLYPH as usize]
+        })
+        .fold(None, |label: Option<Bitmap>, glyph| match label {
+            None => Some(glyph.clone()),
+            Some(label) => Some(label.tile(glyph, overlap)),
+        });
+
+    match label {
+        Some(label) => {
+            let border = (label.height as f64 * 0.05) as usize;
+            label.border(border)
+        }
+        None => Bitmap::filled(0, 0, 1.0),
+    }
+}
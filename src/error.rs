@@ -1,5 +1,5 @@
 use image::error::ImageError;
-use std::io;
+use std::{io, path::PathBuf};
 use thiserror::Error;
 
 /// The error type for this crate.
@@ -15,6 +15,38 @@ pub enum Error {
     InternalError { reason: String },
     #[error("conversion error: {reason:?}")]
     ConversionError { reason: String },
+    #[error("unsupported: {reason:?}")]
+    UnsupportedError { reason: String },
+    #[error("cannot open config file {path:?}: {error:?}")]
+    ConfigOpenError { path: PathBuf, error: io::Error },
+    #[error("config parse error at line {line}: {reason}")]
+    ConfigParseError { line: usize, reason: String },
+    #[error("invalid config: {reason:?}")]
+    InvalidConfigError { reason: String },
+    #[error("config has no sections")]
+    EmptyConfigError,
+    #[error("the first config section must be [net] or [network], but found {found:?}")]
+    InvalidNetSectionError { found: String },
+    #[error("cannot open weights file {path:?}: {error:?}")]
+    WeightsOpenError { path: PathBuf, error: io::Error },
+    #[error("invalid weights: {reason:?}")]
+    InvalidWeightsError { reason: String },
+    #[error("cannot create weights file {path:?}: {error:?}")]
+    WeightsCreateError { path: PathBuf, error: io::Error },
+    #[error("failed to write weights: {error:?}")]
+    WeightsWriteError { error: io::Error },
+    #[error("the input has {found} values, but the network expects {expected}")]
+    InputSizeError { expected: usize, found: usize },
+    #[error("cannot open labels file {path:?}: {error:?}")]
+    LabelsOpenError { path: PathBuf, error: io::Error },
+    #[error("invalid data file: {reason:?}")]
+    InvalidDataFileError { reason: String },
+    #[error("cannot open ground truth file {path:?}: {error:?}")]
+    GroundTruthOpenError { path: PathBuf, error: io::Error },
+    #[error("ground truth parse error at line {line}: {reason}")]
+    GroundTruthParseError { line: usize, reason: String },
+    #[error("there are {labels} labels, but the network has {classes} classes")]
+    LabelCountError { labels: usize, classes: usize },
 }
 
 impl From<ImageError> for Error {
@@ -0,0 +1,481 @@
+//! Detector evaluation, like `darknet detector map`.
+//!
+//! Add the ground truth and the detections of each image to an [Evaluator], then
+//! [evaluate](Evaluator::evaluate) the average precision (AP) per class, the mean average
+//! precision (mAP) over classes, and the counts of true and false positives at a confidence
+//! threshold.
+
+use crate::{bbox::BoundingBox, detections::OwnedDetection, error::Error};
+use std::{fs, path::Path};
+
+/// The IoU thresholds of mAP@0.5:0.95, from 0.5 to 0.95 with a step of 0.05.
+pub const COCO_IOU_THRESHOLDS: [f32; 10] = [0.5, 0.55, 0.6, 0.65, 0.7, 0.75, 0.8, 0.85, 0.9, 0.95];
+
+/// A ground truth object of an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroundTruth {
+    /// The class index.
+    pub class: usize,
+    /// The box normalized by the image size.
+    pub bbox: BoundingBox,
+}
+
+impl GroundTruth {
+    /// Create a ground truth object.
+    pub fn new(class: usize, bbox: BoundingBox) -> Self {
+        Self { class, bbox }
+    }
+
+    /// Parse a darknet label file, where each line is `<class> <x> <y> <width> <height>` with
+    /// the box center and size normalized by the image size.
+    pub fn parse(text: &str) -> Result<Vec<Self>, Error> {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let error = |reason: String| Error::GroundTruthParseError {
+                    line: index + 1,
+                    reason,
+                };
+                let fields: Vec<_> = line.split_whitespace().collect();
+                if fields.len() != 5 {
+                    return Err(error(format!(
+                        "expect 5 fields, but found {}",
+                        fields.len()
+                    )));
+                }
+                let class = fields[0]
+                    .parse()
+                    .map_err(|_| error(format!("invalid class {:?}", fields[0])))?;
+                let mut values = [0.0; 4];
+                for (value, field) in values.iter_mut().zip(&fields[1..]) {
+                    *value = field
+                        .parse()
+                        .map_err(|_| error(format!("invalid number {:?}", field)))?;
+                }
+                let [x, y, w, h] = values;
+                Ok(Self::new(class, BoundingBox::new(x, y, w, h)))
+            })
+            .collect()
+    }
+
+    /// Load a darknet label file, like `data/obj/image.txt` of `data/obj/image.jpg`.
+    pub fn open<P>(path: P) -> Result<Vec<Self>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| Error::GroundTruthOpenError {
+            path: path.to_owned(),
+            error,
+        })?;
+        Self::parse(&text)
+    }
+}
+
+/// The way to average the precisions over recalls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ApMethod {
+    /// The mean of the maximum precisions at 11 recalls from 0 to 1, like PASCAL VOC 2007.
+    Voc11Point,
+    /// The area under the precision-recall curve, like PASCAL VOC 2010-2012.
+    AllPoint,
+    /// The mean of the maximum precisions at 101 recalls from 0 to 1, like MS COCO.
+    Coco101Point,
+}
+
+impl ApMethod {
+    /// Compute the average precision of the precision-recall curve of detections sorted by
+    /// descending score, the same way as `darknet detector map`.
+    pub fn average_precision(self, precisions: &[f64], recalls: &[f64]) -> f64 {
+        let points = match self {
+            Self::Voc11Point => 11,
+            Self::Coco101Point => 101,
+            Self::AllPoint => {
+                let (last_precision, last_recall) = match (precisions.last(), recalls.last()) {
+                    (Some(&precision), Some(&recall)) => (precision, recall),
+                    _ => return 0.0,
+                };
+                let (mut last_precision, mut last_recall) = (last_precision, last_recall);
+                let mut average = 0.0;
+                for (&precision, &recall) in precisions.iter().zip(recalls).rev().skip(1) {
+                    let delta_recall = last_recall - recall;
+                    last_recall = recall;
+                    last_precision = last_precision.max(precision);
+                    average += delta_recall * last_precision;
+                }
+                return average + last_recall * last_precision;
+            }
+        };
+
+        let sum: f64 = (0..points)
+            .map(|point| {
+                let min_recall = point as f64 / (points - 1) as f64;
+                precisions
+                    .iter()
+                    .zip(recalls)
+                    .filter(|&(_, &recall)| recall >= min_recall)
+                    .map(|(&precision, _)| precision)
+                    .fold(0.0, f64::max)
+            })
+            .sum();
+        sum / points as f64
+    }
+}
+
+/// The options of [Evaluator::evaluate].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvalOptions {
+    /// The way to average precisions.
+    pub ap_method: ApMethod,
+    /// The IoU threshold of the AP, and of the counts at the confidence threshold.
+    pub iou_threshold: f32,
+    /// The minimum score of the detections that are counted as true or false positives.
+    pub conf_threshold: f32,
+}
+
+impl Default for EvalOptions {
+    /// Get the options of `darknet detector map`.
+    fn default() -> Self {
+        Self {
+            ap_method: ApMethod::AllPoint,
+            iou_threshold: 0.5,
+            conf_threshold: 0.25,
+        }
+    }
+}
+
+/// The counts of the detections above a confidence threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Counts {
+    /// The number of detections matching a ground truth object.
+    pub true_positives: usize,
+    /// The number of detections without a matching ground truth object, including duplicates.
+    pub false_positives: usize,
+    /// The number of ground truth objects without a matching detection.
+    pub false_negatives: usize,
+    /// The sum of the IoUs of true positives divided by TP + FP, like `darknet detector map`.
+    pub average_iou: f32,
+}
+
+impl Counts {
+    /// Get TP / (TP + FP), or 0 if there are no positives.
+    pub fn precision(&self) -> f32 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    /// Get TP / (TP + FN), or 0 if there is no ground truth.
+    pub fn recall(&self) -> f32 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+
+    /// Get the harmonic mean of the precision and the recall.
+    pub fn f1(&self) -> f32 {
+        let (precision, recall) = (self.precision(), self.recall());
+        if precision + recall > 0.0 {
+            2.0 * precision * recall / (precision + recall)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The evaluation of a class.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassEvaluation {
+    /// The class index.
+    pub class: usize,
+    /// The number of ground truth objects.
+    pub num_truths: usize,
+    /// The number of detections with a positive score.
+    pub num_detections: usize,
+    /// The AP at the IoU threshold of the options.
+    pub ap: f32,
+    /// The mean AP over [COCO_IOU_THRESHOLDS].
+    pub ap50_95: f32,
+    /// The counts at the confidence and IoU thresholds of the options.
+    pub counts: Counts,
+}
+
+/// The evaluation of a detector.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Evaluation {
+    /// The IoU threshold of the mAP and the counts.
+    pub iou_threshold: f32,
+    /// The confidence threshold of the counts.
+    pub conf_threshold: f32,
+    /// The evaluations per class.
+    pub classes: Vec<ClassEvaluation>,
+    /// The mAP at the IoU threshold of the options, such as mAP@0.5.
+    pub map: f32,
+    /// The mAP@0.5:0.95, which is averaged over [COCO_IOU_THRESHOLDS].
+    pub map50_95: f32,
+    /// The counts over all classes.
+    pub counts: Counts,
+}
+
+/// A detection score of a class, matched with the most overlapping ground truth.
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    class: usize,
+    score: f32,
+    /// The global index and IoU of the ground truth object.
+    truth: Option<(usize, f32)>,
+}
+
+/// The accumulator of ground truth and detections over images.
+#[derive(Debug, Clone, Default)]
+pub struct Evaluator {
+    num_classes: usize,
+    num_images: usize,
+    /// The number of ground truth objects per class.
+    truth_counts: Vec<usize>,
+    num_truths: usize,
+    /// The records in the order of addition.
+    records: Vec<Record>,
+}
+
+impl Evaluator {
+    /// Create an empty evaluator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the ground truth and the detections of an image.
+    ///
+    /// The boxes must be in the same coordinates, such as normalized by the image size. Like
+    /// libdarknet, each class with a positive probability counts as a detection, so run NMS
+    /// before adding the detections.
+    pub fn add_image(&mut self, truths: &[GroundTruth], detections: &[OwnedDetection]) {
+        let num_classes = truths
+            .iter()
+            .map(|truth| truth.class + 1)
+            .chain(detections.iter().map(OwnedDetection::num_classes))
+            .fold(self.num_classes, usize::max);
+        self.num_classes = num_classes;
+        self.truth_counts.resize(num_classes, 0);
+        for truth in truths {
+            self.truth_counts[truth.class] += 1;
+        }
+
+        for detection in detections {
+            let bbox = detection.bounding_box();
+            for (class, &score) in detection.probabilities.iter().enumerate() {
+                if score <= 0.0 {
+                    continue;
+                }
+                let truth = truths
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, truth)| truth.class == class)
+                    .map(|(index, truth)| (self.num_truths + index, bbox.iou(&truth.bbox)))
+                    .filter(|&(_, iou)| iou > 0.0)
+                    .fold(
+                        None,
+                        |best: Option<(usize, f32)>, (index, iou)| match best {
+                            Some((_, best_iou)) if iou <= best_iou => best,
+                            _ => Some((index, iou)),
+                        },
+                    );
+                self.records.push(Record {
+                    class,
+                    score,
+                    truth,
+                });
+            }
+        }
+
+        self.num_truths += truths.len();
+        self.num_images += 1;
+    }
+
+    /// Get the number of added images.
+    pub fn num_images(&self) -> usize {
+        self.num_images
+    }
+
+    /// Get the number of classes, which is the largest class count of the added ground truth
+    /// and detections.
+    pub fn num_classes(&self) -> usize {
+        self.num_classes
+    }
+
+    /// Get the AP per class at an IoU threshold.
+    ///
+    /// A detection is a true positive if its IoU with the most overlapping ground truth of
+    /// the same class is above the threshold, and the ground truth is not matched by a
+    /// detection of a higher score.
+    pub fn average_precisions(&self, iou_threshold: f32, method: ApMethod) -> Vec<f32> {
+        let mut matched = vec![false; self.num_truths];
+        let mut curves = vec![(vec![], vec![]); self.num_classes];
+        let mut counts = vec![(0usize, 0usize); self.num_classes];
+
+        for record in self.ranked_records() {
+            let (true_positives, false_positives) = &mut counts[record.class];
+            match record.truth {
+                Some((index, iou)) if iou > iou_threshold && !matched[index] => {
+                    matched[index] = true;
+                    *true_positives += 1;
+                }
+                _ => *false_positives += 1,
+            }
+            let (precisions, recalls) = &mut curves[record.class];
+            precisions.push(*true_positives as f64 / (*true_positives + *false_positives) as f64);
+            recalls.push(match self.truth_counts[record.class] {
+                0 => 0.0,
+                num_truths => *true_positives as f64 / num_truths as f64,
+            });
+        }
+
+        curves
+            .iter()
+            .map(|(precisions, recalls)| method.average_precision(precisions, recalls) as f32)
+            .collect()
+    }
+
+    /// Get the mean of the APs over all classes at an IoU threshold.
+    pub fn mean_average_precision(&self, iou_threshold: f32, method: ApMethod) -> f32 {
+        mean(&self.average_precisions(iou_threshold, method))
+    }
+
+    /// Count the true and false positives of the detections above a confidence threshold per
+    /// class.
+    ///
+    /// The detections are matched in descending score order, so that a ground truth object is
+    /// matched at most once.
+    pub fn counts(&self, iou_threshold: f32, conf_threshold: f32) -> Vec<Counts> {
+        self.count_positives(iou_threshold, conf_threshold)
+            .into_iter()
+            .map(|(counts, _)| counts)
+            .collect()
+    }
+
+    /// Get the counts and the sum of the IoUs of true positives per class.
+    fn count_positives(&self, iou_threshold: f32, conf_threshold: f32) -> Vec<(Counts, f64)> {
+        let mut matched = vec![false; self.num_truths];
+        let mut counts = vec![(Counts::default(), 0.0); self.num_classes];
+
+        for record in self
+            .ranked_records()
+            .into_iter()
+            .take_while(|record| record.score > conf_threshold)
+        {
+            let (counts, iou_sum) = &mut counts[record.class];
+            match record.truth {
+                Some((index, iou)) if iou > iou_threshold && !matched[index] => {
+                    matched[index] = true;
+                    counts.true_positives += 1;
+                    *iou_sum += iou as f64;
+                }
+                _ => counts.false_positives += 1,
+            }
+        }
+        for ((counts, iou_sum), &num_truths) in counts.iter_mut().zip(&self.truth_counts) {
+            counts.false_negatives = num_truths - counts.true_positives;
+            counts.average_iou = average_iou(counts, *iou_sum);
+        }
+        counts
+    }
+
+    /// Get the records sorted by descending score, where ties are in the order of addition.
+    fn ranked_records(&self) -> Vec<&Record> {
+        let mut records: Vec<_> = self.records.iter().collect();
+        records.sort_by(|a, b| b.score.total_cmp(&a.score));
+        records
+    }
+
+    /// Evaluate the detections of the added images.
+    pub fn evaluate(&self, options: &EvalOptions) -> Evaluation {
+        let EvalOptions {
+            ap_method,
+            iou_threshold,
+            conf_threshold,
+        } = *options;
+
+        let aps = self.average_precisions(iou_threshold, ap_method);
+        let coco_aps: Vec<_> = COCO_IOU_THRESHOLDS
+            .iter()
+            .map(|&threshold| self.average_precisions(threshold, ap_method))
+            .collect();
+        let class_counts = self.count_positives(iou_threshold, conf_threshold);
+
+        let classes: Vec<_> = (0..self.num_classes)
+            .map(|class| ClassEvaluation {
+                class,
+                num_truths: self.truth_counts[class],
+                num_detections: self
+                    .records
+                    .iter()
+                    .filter(|record| record.class == class)
+                    .count(),
+                ap: aps[class],
+                ap50_95: mean(&coco_aps.iter().map(|aps| aps[class]).collect::<Vec<_>>()),
+                counts: class_counts[class].0,
+            })
+            .collect();
+
+        let (mut counts, iou_sum) =
+            class_counts
+                .iter()
+                .fold((Counts::default(), 0.0), |(sum, sum_iou), (counts, iou)| {
+                    let sum = Counts {
+                        true_positives: sum.true_positives + counts.true_positives,
+                        false_positives: sum.false_positives + counts.false_positives,
+                        false_negatives: sum.false_negatives + counts.false_negatives,
+                        average_iou: 0.0,
+                    };
+                    (sum, sum_iou + iou)
+                });
+        counts.average_iou = average_iou(&counts, iou_sum);
+
+        Evaluation {
+            iou_threshold,
+            conf_threshold,
+            map: mean(&aps),
+            map50_95: mean(
+                &classes
+                    .iter()
+                    .map(|class| class.ap50_95)
+                    .collect::<Vec<_>>(),
+            ),
+            classes,
+            counts,
+        }
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f32 {
+    if denominator > 0 {
+        numerator as f32 / denominator as f32
+    } else {
+        0.0
+    }
+}
+
+fn average_iou(counts: &Counts, iou_sum: f64) -> f32 {
+    let positives = counts.true_positives + counts.false_positives;
+    if positives > 0 {
+        (iou_sum / positives as f64) as f32
+    } else {
+        0.0
+    }
+}
+
+fn mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f32>() / values.len() as f32
+    }
+}
@@ -0,0 +1,374 @@
+//! Writing detections in the formats of labeling tools and evaluation scripts.
+//!
+//! Collect the detections of each image into an [ImageDetections], with the boxes normalized
+//! by the image size, then write them as MS COCO results with [write_coco_json], as the
+//! `-out result.json` of `darknet detector test` with [write_darknet_json], as Pascal VOC
+//! annotations with [save_voc_xml], or as darknet label files with [save_yolo_txt].
+
+use crate::{bbox::BoundingBox, detections::OwnedDetection, error::Error, labels::Labels};
+use std::{
+    borrow::{Borrow, Cow},
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// The MS COCO category IDs of the 80 classes of `data/coco.names`.
+pub const COCO_CATEGORY_IDS: [u64; 80] = [
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 27, 28,
+    31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55,
+    56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 67, 70, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 84,
+    85, 86, 87, 88, 89, 90,
+];
+
+/// The detections of an image.
+#[derive(Debug, Clone)]
+pub struct ImageDetections {
+    /// The path of the image file.
+    pub path: PathBuf,
+    /// The image ID of MS COCO results.
+    pub id: u64,
+    /// The image width in pixels.
+    pub width: usize,
+    /// The image height in pixels.
+    pub height: usize,
+    /// The detections with boxes normalized by the image size.
+    pub detections: Vec<OwnedDetection>,
+}
+
+impl ImageDetections {
+    /// Create the detections of an image.
+    ///
+    /// Like libdarknet, the ID is parsed from the leading digits of the file name, such as 139
+    /// for `000000000139.jpg`, or 0 if there are none.
+    pub fn new<P>(path: P, width: usize, height: usize, detections: Vec<OwnedDetection>) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let id = path
+            .file_stem()
+            .and_then(|stem| {
+                let stem = stem.to_string_lossy();
+                let digits = stem
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(stem.len());
+                stem[..digits].parse().ok()
+            })
+            .unwrap_or(0);
+        Self {
+            path,
+            id,
+            width,
+            height,
+            detections,
+        }
+    }
+
+    /// Get the box of a detection in pixels.
+    fn pixel_box(&self, detection: &OwnedDetection) -> BoundingBox {
+        detection.bounding_box().to_pixels(self.width, self.height)
+    }
+}
+
+/// Write MS COCO detection results, a JSON array with a result per class probability above zero.
+///
+/// The category IDs are the class indexes, or looked up from \[category_ids\] such as
+/// [COCO_CATEGORY_IDS]. Boxes are `[left, top, width, height]` in pixels, clipped to the image.
+pub fn write_coco_json<W, I>(
+    mut writer: W,
+    images: I,
+    category_ids: Option<&[u64]>,
+) -> Result<(), Error>
+where
+    W: Write,
+    I: IntoIterator,
+    I::Item: Borrow<ImageDetections>,
+{
+    let mut first = true;
+    writer.write_all(b"[\n")?;
+    for image in images {
+        let image = image.borrow();
+        for detection in &image.detections {
+            let [left, top, right, bottom] = image.pixel_box(detection).to_xyxy();
+            let (left, top) = (left.max(0.0), top.max(0.0));
+            let right = right.min(image.width as f32);
+            let bottom = bottom.min(image.height as f32);
+
+            for (class, &prob) in detection.probabilities.iter().enumerate() {
+                if prob <= 0.0 {
+                    continue;
+                }
+                let category_id = match category_ids {
+                    Some(ids) => ids
+                        .get(class)
+                        .copied()
+                        .ok_or_else(|| Error::ConversionError {
+                            reason: format!("class {} has no category ID", class),
+                        })?,
+                    None => class as u64,
+                };
+                if !first {
+                    writer.write_all(b",\n")?;
+                }
+                first = false;
+                write!(
+                    writer,
+                    "{{\"image_id\":{}, \"category_id\":{}, \"bbox\":[{:.6}, {:.6}, {:.6}, {:.6}], \"score\":{:.6}}}",
+                    image.id,
+                    category_id,
+                    left,
+                    top,
+                    right - left,
+                    bottom - top,
+                    prob
+                )?;
+            }
+        }
+    }
+    writer.write_all(b"\n]\n")?;
+    Ok(())
+}
+
+/// Write the detections like `darknet detector test -out result.json`.
+///
+/// Each image is an object with a 1-based `frame_id`, the `filename` and the `objects` of
+/// the class probabilities above 0.005, with boxes normalized by the image size. Classes with
+/// labels starting with `dont_show` are skipped.
+pub fn write_darknet_json<W, I>(mut writer: W, images: I, labels: &Labels) -> Result<(), Error>
+where
+    W: Write,
+    I: IntoIterator,
+    I::Item: Borrow<ImageDetections>,
+{
+    const THRESH: f32 = 0.005;
+
+    writer.write_all(b"[\n")?;
+    for (index, image) in images.into_iter().enumerate() {
+        let image = image.borrow();
+        if index > 0 {
+            writer.write_all(b", \n")?;
+        }
+        write!(
+            writer,
+            "{{\n \"frame_id\":{}, \n \"filename\":\"{}\", \n \"objects\": [ \n",
+            index + 1,
+            escape_json(&image.path.display().to_string())
+        )?;
+
+        let mut first = true;
+        for detection in &image.detections {
+            let bbox = detection.bbox;
+            for (class, &prob) in detection.probabilities.iter().enumerate() {
+                let name = class_name(labels, class);
+                if prob <= THRESH || name.starts_with("dont_show") {
+                    continue;
+                }
+                if !first {
+                    writer.write_all(b", \n")?;
+                }
+                first = false;
+                write!(
+                    writer,
+                    "  {{\"class_id\":{}, \"name\":\"{}\", \"relative_coordinates\":{{\"center_x\":{:.6}, \"center_y\":{:.6}, \"width\":{:.6}, \"height\":{:.6}}}, \"confidence\":{:.6}}}",
+                    class,
+                    escape_json(&name),
+                    bbox.x,
+                    bbox.y,
+                    bbox.w,
+                    bbox.h,
+                    prob
+                )?;
+            }
+        }
+        writer.write_all(b"\n ] \n}")?;
+    }
+    writer.write_all(b"\n]")?;
+    Ok(())
+}
+
+/// Write the Pascal VOC annotation of an image.
+///
+/// Each detection with a class probability above zero is an object of its most probable
+/// class. Boxes are 1-based pixel coordinates clipped to the image, like the VOC results of
+/// `darknet detector valid`.
+pub fn write_voc_xml<W>(
+    mut writer: W,
+    image: &ImageDetections,
+    labels: &Labels,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    let file_name = image
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let folder = image
+        .path
+        .parent()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+
+    writeln!(writer, "<annotation>")?;
+    writeln!(writer, "\t<folder>{}</folder>", escape_xml(&folder))?;
+    writeln!(writer, "\t<filename>{}</filename>", escape_xml(&file_name))?;
+    writeln!(
+        writer,
+        "\t<path>{}</path>",
+        escape_xml(&image.path.display().to_string())
+    )?;
+    writeln!(
+        writer,
+        "\t<source>\n\t\t<database>Unknown</database>\n\t</source>"
+    )?;
+    writeln!(
+        writer,
+        "\t<size>\n\t\t<width>{}</width>\n\t\t<height>{}</height>\n\t\t<depth>3</depth>\n\t</size>",
+        image.width, image.height
+    )?;
+    writeln!(writer, "\t<segmented>0</segmented>")?;
+
+    for detection in &image.detections {
+        let (class, _) = match detection.best_class(Some(0.0)) {
+            Some(best) => best,
+            None => continue,
+        };
+        let [left, top, right, bottom] = image.pixel_box(detection).to_xyxy();
+        let (width, height) = (image.width as f32, image.height as f32);
+        let xmin = (left + 1.0).max(1.0).round();
+        let ymin = (top + 1.0).max(1.0).round();
+        let xmax = (right + 1.0).min(width).round();
+        let ymax = (bottom + 1.0).min(height).round();
+
+        writeln!(writer, "\t<object>")?;
+        writeln!(
+            writer,
+            "\t\t<name>{}</name>",
+            escape_xml(&class_name(labels, class))
+        )?;
+        writeln!(writer, "\t\t<pose>Unspecified</pose>")?;
+        writeln!(writer, "\t\t<truncated>0</truncated>")?;
+        writeln!(writer, "\t\t<difficult>0</difficult>")?;
+        writeln!(
+            writer,
+            "\t\t<bndbox>\n\t\t\t<xmin>{}</xmin>\n\t\t\t<ymin>{}</ymin>\n\t\t\t<xmax>{}</xmax>\n\t\t\t<ymax>{}</ymax>\n\t\t</bndbox>",
+            xmin, ymin, xmax, ymax
+        )?;
+        writeln!(writer, "\t</object>")?;
+    }
+    writeln!(writer, "</annotation>")?;
+    Ok(())
+}
+
+/// Write the darknet label file of an image, like `darknet detector test -save_labels`.
+///
+/// Each detection with a class probability above zero is a line of
+/// `<class> <x> <y> <width> <height>` with its most probable class and normalized box.
+pub fn write_yolo_txt<W>(mut writer: W, image: &ImageDetections) -> Result<(), Error>
+where
+    W: Write,
+{
+    for detection in &image.detections {
+        if let Some((class, _)) = detection.best_class(Some(0.0)) {
+            let bbox = detection.bbox;
+            writeln!(
+                writer,
+                "{} {:.4} {:.4} {:.4} {:.4}",
+                class, bbox.x, bbox.y, bbox.w, bbox.h
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Save a Pascal VOC annotation per image in a directory, named after the image file with
+/// the `.xml` extension.
+pub fn save_voc_xml<P, I>(dir: P, images: I, labels: &Labels) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    I: IntoIterator,
+    I::Item: Borrow<ImageDetections>,
+{
+    save_files(dir.as_ref(), images, "xml", |writer, image| {
+        write_voc_xml(writer, image, labels)
+    })
+}
+
+/// Save a darknet label file per image in a directory, named after the image file with the
+/// `.txt` extension.
+pub fn save_yolo_txt<P, I>(dir: P, images: I) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    I: IntoIterator,
+    I::Item: Borrow<ImageDetections>,
+{
+    save_files(dir.as_ref(), images, "txt", |writer, image| {
+        write_yolo_txt(writer, image)
+    })
+}
+
+fn save_files<I, F>(dir: &Path, images: I, extension: &str, mut write: F) -> Result<(), Error>
+where
+    I: IntoIterator,
+    I::Item: Borrow<ImageDetections>,
+    F: FnMut(&mut BufWriter<File>, &ImageDetections) -> Result<(), Error>,
+{
+    fs::create_dir_all(dir)?;
+    for image in images {
+        let image = image.borrow();
+        let stem = image
+            .path
+            .file_stem()
+            .ok_or_else(|| Error::ConversionError {
+                reason: format!("{} is not a file path", image.path.display()),
+            })?;
+        let mut file_name = stem.to_owned();
+        file_name.push(".");
+        file_name.push(extension);
+        let mut writer = BufWriter::new(File::create(dir.join(file_name))?);
+        write(&mut writer, image)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Get the label of a class, or the class index if it has no label.
+fn class_name(labels: &Labels, class: usize) -> Cow<'_, str> {
+    labels
+        .get(class)
+        .map_or_else(|| Cow::Owned(class.to_string()), Cow::Borrowed)
+}
+
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
@@ -1,13 +1,15 @@
-use crate::{error::Error, BBox};
+use crate::{bbox::BoundingBox, error::Error, letterbox::LetterBox, BBox};
 use darknet_sys as sys;
 use image::{DynamicImage, ImageBuffer, Pixel};
 use std::{
+    alloc,
     borrow::{Borrow, Cow},
     convert::TryFrom,
-    ops::Deref,
+    mem,
+    ops::{Deref, Index, IndexMut, Range},
     os::raw::c_int,
     path::Path,
-    slice,
+    slice::{self, ChunksExact, ChunksExactMut},
 };
 
 pub trait ConvertSubpixel
@@ -79,21 +81,66 @@ impl ConvertSubpixel for f64 {
 }
 
 /// The image type used by darknet.
+///
+/// The pixels are stored in planar order, so the value of channel `c` at row `y` and column
+/// `x` is at `(c * height + y) * width + x`.
 #[derive(Debug)]
 pub struct Image {
-    pub image: sys::image,
+    image: sys::image,
 }
 
 impl Image {
     /// Returns an image filled with zeros.
     pub fn zeros(w: usize, h: usize, c: usize) -> Image {
-        unsafe {
-            Image {
-                image: sys::make_image(w as c_int, h as c_int, c as c_int),
-            }
+        let len = w
+            .checked_mul(h)
+            .and_then(|len| len.checked_mul(c))
+            .filter(|&len| c_int::try_from(len).is_ok())
+            .expect("the image is too large");
+        // allocated like make_image, so that free_image in libdarknet can free it
+        let data = unsafe { libc::calloc(len.max(1), mem::size_of::<f32>()) as *mut f32 };
+        if data.is_null() {
+            alloc::handle_alloc_error(alloc::Layout::array::<f32>(len.max(1)).unwrap());
+        }
+        Image {
+            image: sys::image {
+                w: w as c_int,
+                h: h as c_int,
+                c: c as c_int,
+                data,
+            },
         }
     }
 
+    /// Take the ownership of a libdarknet image.
+    ///
+    /// # Safety
+    /// The image data must be null, or a buffer of `w * h * c` floats allocated by `malloc`,
+    /// like the images returned by libdarknet. It must not be used or freed elsewhere.
+    pub unsafe fn from_raw(image: sys::image) -> Self {
+        Image { image }
+    }
+
+    /// Release the libdarknet image, which must be freed with `free_image`.
+    pub fn into_raw(self) -> sys::image {
+        let image = self.image;
+        mem::forget(self);
+        image
+    }
+
+    /// Get the libdarknet image to pass it to libdarknet functions that do not modify it.
+    pub fn as_raw(&self) -> &sys::image {
+        &self.image
+    }
+
+    /// Get the libdarknet image to pass it to libdarknet functions that modify it.
+    ///
+    /// # Safety
+    /// The size and data pointer must keep the requirements of [Image::from_raw].
+    pub unsafe fn as_raw_mut(&mut self) -> &mut sys::image {
+        &mut self.image
+    }
+
     /// Open image from a file.
     pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, Error> {
         let image: Self = image::open(filename)?.into();
@@ -102,14 +149,26 @@ impl Image {
 
     /// Resize the image without keeping the ratio.
     pub fn resize(&self, w: usize, h: usize) -> Self {
-        let image = unsafe { sys::resize_image(self.image, w as c_int, h as c_int) };
-        Image { image }
+        unsafe { Self::from_raw(sys::resize_image(self.image, w as c_int, h as c_int)) }
     }
 
     /// Resize the image while keeping the ratio.
+    ///
+    /// An empty image gives an image of gray padding.
     pub fn letter_box(&self, w: usize, h: usize) -> Self {
-        let image = unsafe { sys::letterbox_image(self.image, w as c_int, h as c_int) };
-        Image { image }
+        if self.width() == 0 || self.height() == 0 {
+            // libdarknet divides by the image size
+            let mut boxed = Self::zeros(w, h, self.channels());
+            boxed.get_data_mut().fill(0.5);
+            return boxed;
+        }
+        unsafe { Self::from_raw(sys::letterbox_image(self.image, w as c_int, h as c_int)) }
+    }
+
+    /// Resize the image while keeping the ratio, and return the transform of the boxes with it.
+    pub fn letter_box_with_transform(&self, w: usize, h: usize) -> (Self, LetterBox) {
+        let transform = LetterBox::new(self.width(), self.height(), w, h);
+        (self.letter_box(w, h), transform)
     }
 
     /// Crop a bounding box from the image.
@@ -117,24 +176,24 @@ impl Image {
     where
         B: Borrow<BBox>,
     {
-        let BBox { x, y, w, h } = *bbox.borrow();
-        let image_width = self.width() as f32;
-        let image_height = self.height() as f32;
-
-        let left = (x - w / 2.0) * image_width;
-        let top = (y - h / 2.0) * image_height;
-        let width = w * image_width;
-        let height = h * image_height;
+        let [left, top, width, height] = BoundingBox::from(bbox.borrow())
+            .to_pixels(self.width(), self.height())
+            .to_xywh();
+        self.crop(left as isize, top as isize, width as usize, height as usize)
+    }
+
+    /// Crop a rectangle in pixels from the image.
+    ///
+    /// Like libdarknet, the pixels outside the image are filled with the nearest edge pixels.
+    pub fn crop(&self, left: isize, top: isize, width: usize, height: usize) -> Image {
         unsafe {
-            Image {
-                image: sys::crop_image(
-                    self.image,
-                    left as c_int,
-                    top as c_int,
-                    width as c_int,
-                    height as c_int,
-                ),
-            }
+            Self::from_raw(sys::crop_image(
+                self.image,
+                left as c_int,
+                top as c_int,
+                width as c_int,
+                height as c_int,
+            ))
         }
     }
 
@@ -145,22 +204,88 @@ impl Image {
 
     /// Returns pixel values as a slice.
     pub fn get_data(&self) -> &[f32] {
-        return unsafe {
-            slice::from_raw_parts(
-                self.image.data,
-                (self.image.h * self.image.w * self.image.c) as usize,
-            )
-        };
+        if self.image.data.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.image.data, self.len()) }
     }
 
     /// Returns pixel values as a mutable slice.
-    pub fn get_data_mut(&self) -> &mut [f32] {
-        return unsafe {
-            slice::from_raw_parts_mut(
-                self.image.data,
-                (self.image.h * self.image.w * self.image.c) as usize,
-            )
-        };
+    pub fn get_data_mut(&mut self) -> &mut [f32] {
+        if self.image.data.is_null() {
+            return &mut [];
+        }
+        unsafe { slice::from_raw_parts_mut(self.image.data, self.len()) }
+    }
+
+    /// Get the value of channel `c` at row `y` and column `x`, or [None] if it is outside the
+    /// image.
+    pub fn get_pixel(&self, c: usize, y: usize, x: usize) -> Option<f32> {
+        let index = self.pixel_index(c, y, x)?;
+        Some(self.get_data()[index])
+    }
+
+    /// Get the mutable value of channel `c` at row `y` and column `x`, or [None] if it is
+    /// outside the image.
+    pub fn get_pixel_mut(&mut self, c: usize, y: usize, x: usize) -> Option<&mut f32> {
+        let index = self.pixel_index(c, y, x)?;
+        Some(&mut self.get_data_mut()[index])
+    }
+
+    /// Set the value of channel `c` at row `y` and column `x`.
+    ///
+    /// # Panics
+    /// Panics if the pixel is outside the image.
+    pub fn set_pixel(&mut self, c: usize, y: usize, x: usize, value: f32) {
+        self[(c, y, x)] = value;
+    }
+
+    /// Get the values of a channel.
+    ///
+    /// # Panics
+    /// Panics if the channel is out of range.
+    pub fn plane(&self, c: usize) -> &[f32] {
+        let range = self.plane_range(c);
+        &self.get_data()[range]
+    }
+
+    /// Get the mutable values of a channel.
+    ///
+    /// # Panics
+    /// Panics if the channel is out of range.
+    pub fn plane_mut(&mut self, c: usize) -> &mut [f32] {
+        let range = self.plane_range(c);
+        &mut self.get_data_mut()[range]
+    }
+
+    /// Iterate over the channels.
+    pub fn planes(&self) -> ChunksExact<'_, f32> {
+        let size = (self.width() * self.height()).max(1);
+        self.get_data().chunks_exact(size)
+    }
+
+    /// Iterate over the mutable channels.
+    pub fn planes_mut(&mut self) -> ChunksExactMut<'_, f32> {
+        let size = (self.width() * self.height()).max(1);
+        self.get_data_mut().chunks_exact_mut(size)
+    }
+
+    /// Get the values of a row in a channel.
+    ///
+    /// # Panics
+    /// Panics if the channel or row is out of range.
+    pub fn row(&self, c: usize, y: usize) -> &[f32] {
+        let range = self.row_range(c, y);
+        &self.get_data()[range]
+    }
+
+    /// Get the mutable values of a row in a channel.
+    ///
+    /// # Panics
+    /// Panics if the channel or row is out of range.
+    pub fn row_mut(&mut self, c: usize, y: usize) -> &mut [f32] {
+        let range = self.row_range(c, y);
+        &mut self.get_data_mut()[range]
     }
 
     /// Get the image width.
@@ -183,6 +308,40 @@ impl Image {
         (self.channels(), self.height(), self.width())
     }
 
+    /// Get the number of values.
+    fn len(&self) -> usize {
+        self.width() * self.height() * self.channels()
+    }
+
+    fn pixel_index(&self, c: usize, y: usize, x: usize) -> Option<usize> {
+        let (channels, height, width) = self.shape();
+        (c < channels && y < height && x < width).then(|| (c * height + y) * width + x)
+    }
+
+    fn plane_range(&self, c: usize) -> Range<usize> {
+        let (channels, height, width) = self.shape();
+        assert!(
+            c < channels,
+            "channel {} out of range for {} channels",
+            c,
+            channels
+        );
+        c * height * width..(c + 1) * height * width
+    }
+
+    fn row_range(&self, c: usize, y: usize) -> Range<usize> {
+        let (channels, height, width) = self.shape();
+        assert!(
+            c < channels && y < height,
+            "row ({}, {}) out of range for {} channels and {} rows",
+            c,
+            y,
+            channels,
+            height
+        );
+        (c * height + y) * width..(c * height + y + 1) * width
+    }
+
     /// Convert Image to ImageBuffer from 'image' crate
     pub fn to_image_buffer<P>(&self) -> Result<ImageBuffer<P, Vec<P::Subpixel>>, Error>
     where
@@ -221,18 +380,51 @@ impl Image {
 impl Clone for Image {
     /// Make a deep-copy of the image.
     fn clone(&self) -> Image {
-        let sys::image { w, h, c, .. } = self.image;
-        let image = Self::zeros(w as usize, h as usize, c as usize);
-        let from_slice = self.get_data();
-        let to_slice = image.get_data_mut();
-        to_slice.copy_from_slice(from_slice);
+        let (channels, height, width) = self.shape();
+        let mut image = Self::zeros(width, height, channels);
+        image.get_data_mut().copy_from_slice(self.get_data());
         image
     }
 }
 
 impl Drop for Image {
     fn drop(&mut self) {
-        unsafe { sys::free_image(self.image) }
+        // the same as free_image in libdarknet
+        unsafe { libc::free(self.image.data as *mut libc::c_void) }
+    }
+}
+
+impl Index<(usize, usize, usize)> for Image {
+    type Output = f32;
+
+    /// Get the value of channel `c` at row `y` and column `x` by `(c, y, x)`.
+    fn index(&self, (c, y, x): (usize, usize, usize)) -> &f32 {
+        match self.pixel_index(c, y, x) {
+            Some(index) => &self.get_data()[index],
+            None => panic!(
+                "pixel ({}, {}, {}) out of range for the shape {:?}",
+                c,
+                y,
+                x,
+                self.shape()
+            ),
+        }
+    }
+}
+
+impl IndexMut<(usize, usize, usize)> for Image {
+    /// Get the mutable value of channel `c` at row `y` and column `x` by `(c, y, x)`.
+    fn index_mut(&mut self, (c, y, x): (usize, usize, usize)) -> &mut f32 {
+        match self.pixel_index(c, y, x) {
+            Some(index) => &mut self.get_data_mut()[index],
+            None => panic!(
+                "pixel ({}, {}, {}) out of range for the shape {:?}",
+                c,
+                y,
+                x,
+                self.shape()
+            ),
+        }
     }
 }
 
@@ -277,8 +469,9 @@ where
         let c = P::CHANNEL_COUNT as usize;
         let n_components = w * h * c;
 
-        let image = unsafe { sys::make_image(w as i32, h as i32, c as i32) };
-        let slice = unsafe { slice::from_raw_parts_mut(image.data, n_components) };
+        let mut image = Self::zeros(w, h, c);
+        let slice = image.get_data_mut();
+        debug_assert_eq!(slice.len(), n_components);
 
         buffer
             .enumerate_pixels()
@@ -299,7 +492,7 @@ where
                 slice[index] = component;
             });
 
-        Self { image }
+        image
     }
 }
 
@@ -1,5 +1,7 @@
+use crate::error::Error;
 use darknet_sys as sys;
 use num_derive::FromPrimitive;
+use std::str::FromStr;
 
 /// Layer types.
 #[repr(usize)]
@@ -42,6 +44,109 @@ pub enum LayerType {
     Normalization = sys::LAYER_TYPE_NORMALIZATION as usize,
     ScaleChannels = sys::LAYER_TYPE_SCALE_CHANNELS as usize,
     Deconvolutional = sys::LAYER_TYPE_DECONVOLUTIONAL as usize,
+    History = sys::LAYER_TYPE_HISTORY as usize,
+    Implicit = sys::LAYER_TYPE_IMPLICIT as usize,
+    Contrastive = sys::LAYER_TYPE_CONTRASTIVE as usize,
+}
+
+impl LayerType {
+    /// Get the section name used in config files, without brackets.
+    ///
+    /// Layer types that cannot be declared in a config file get a lowercase name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Gru => "gru",
+            Self::Rnn => "rnn",
+            Self::Sam => "sam",
+            Self::Cost => "cost",
+            Self::Crnn => "crnn",
+            Self::Crop => "crop",
+            Self::Iseg => "iseg",
+            Self::Lstm => "lstm",
+            Self::Xnor => "xnor",
+            Self::Yolo => "yolo",
+            Self::Blank => "blank",
+            Self::Empty => "empty",
+            Self::Local => "local",
+            Self::Reorg => "reorg3d",
+            Self::Route => "route",
+            Self::Active => "activation",
+            Self::L2Norm => "l2norm",
+            Self::Region => "region",
+            Self::Avgpool => "avgpool",
+            Self::Dropout => "dropout",
+            Self::Logxent => "logxent",
+            Self::Maxpool => "maxpool",
+            Self::Network => "net",
+            Self::Softmax => "softmax",
+            Self::Shortcut => "shortcut",
+            Self::Upsample => "upsample",
+            Self::Batchnorm => "batchnorm",
+            Self::Connected => "connected",
+            Self::ConvLstm => "conv_lstm",
+            Self::Detection => "detection",
+            Self::ReorgOld => "reorg",
+            Self::Convolutional => "convolutional",
+            Self::GaussianYolo => "Gaussian_yolo",
+            Self::LocalAvgpool => "local_avgpool",
+            Self::Normalization => "normalization",
+            Self::ScaleChannels => "scale_channels",
+            Self::Deconvolutional => "deconvolutional",
+            Self::History => "history",
+            Self::Implicit => "implicit",
+            Self::Contrastive => "contrastive",
+        }
+    }
+}
+
+impl FromStr for LayerType {
+    type Err = Error;
+
+    /// Parse a config section name, without brackets, including the aliases accepted by darknet.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let type_ = match name {
+            "shortcut" => Self::Shortcut,
+            "scale_channels" => Self::ScaleChannels,
+            "sam" => Self::Sam,
+            "crop" => Self::Crop,
+            "cost" => Self::Cost,
+            "detection" => Self::Detection,
+            "region" => Self::Region,
+            "yolo" => Self::Yolo,
+            "Gaussian_yolo" => Self::GaussianYolo,
+            "local" => Self::Local,
+            "conv" | "convolutional" => Self::Convolutional,
+            "activation" => Self::Active,
+            "net" | "network" => Self::Network,
+            "crnn" => Self::Crnn,
+            "gru" => Self::Gru,
+            "lstm" => Self::Lstm,
+            "conv_lstm" => Self::ConvLstm,
+            "history" => Self::History,
+            "rnn" => Self::Rnn,
+            "conn" | "connected" => Self::Connected,
+            "max" | "maxpool" => Self::Maxpool,
+            "local_avg" | "local_avgpool" => Self::LocalAvgpool,
+            "reorg3d" => Self::Reorg,
+            "reorg" => Self::ReorgOld,
+            "avg" | "avgpool" => Self::Avgpool,
+            "dropout" => Self::Dropout,
+            "lrn" | "normalization" => Self::Normalization,
+            "batchnorm" => Self::Batchnorm,
+            "soft" | "softmax" => Self::Softmax,
+            "contrastive" => Self::Contrastive,
+            "route" => Self::Route,
+            "upsample" => Self::Upsample,
+            "empty" | "silence" => Self::Empty,
+            "implicit" => Self::Implicit,
+            _ => {
+                return Err(Error::ConversionError {
+                    reason: format!("unknown layer type {:?}", name),
+                })
+            }
+        };
+        Ok(type_)
+    }
 }
 
 /// Activation types.
@@ -69,6 +174,77 @@ pub enum Activation {
     NormChan = sys::ACTIVATION_NORM_CHAN as usize,
     NormChanSoftmax = sys::ACTIVATION_NORM_CHAN_SOFTMAX as usize,
     NormChanSoftmaxMaxval = sys::ACTIVATION_NORM_CHAN_SOFTMAX_MAXVAL as usize,
+    HardMish = sys::ACTIVATION_HARD_MISH as usize,
+    RevLeaky = sys::ACTIVATION_REVLEAKY as usize,
+}
+
+impl Activation {
+    /// Get the name used in config files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Elu => "elu",
+            Self::Gelu => "gelu",
+            Self::Mish => "mish",
+            Self::Plse => "plse",
+            Self::Ramp => "ramp",
+            Self::Relu => "relu",
+            Self::Selu => "selu",
+            Self::Tanh => "tanh",
+            Self::Leaky => "leaky",
+            Self::Lhtan => "lhtan",
+            Self::Loggy => "loggy",
+            Self::Relie => "relie",
+            Self::Relu6 => "relu6",
+            Self::Stair => "stair",
+            Self::Swish => "swish",
+            Self::Linear => "linear",
+            Self::Hardtan => "hardtan",
+            Self::Logistic => "logistic",
+            Self::NormChan => "normalize_channels",
+            Self::NormChanSoftmax => "normalize_channels_softmax",
+            Self::NormChanSoftmaxMaxval => "normalize_channels_softmax_maxval",
+            Self::HardMish => "hard_mish",
+            Self::RevLeaky => "revleaky",
+        }
+    }
+}
+
+impl FromStr for Activation {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let activation = match name {
+            "elu" => Self::Elu,
+            "gelu" => Self::Gelu,
+            "mish" => Self::Mish,
+            "plse" => Self::Plse,
+            "ramp" => Self::Ramp,
+            "relu" => Self::Relu,
+            "selu" => Self::Selu,
+            "tanh" => Self::Tanh,
+            "leaky" => Self::Leaky,
+            "lhtan" => Self::Lhtan,
+            "loggy" => Self::Loggy,
+            "relie" => Self::Relie,
+            "relu6" => Self::Relu6,
+            "stair" => Self::Stair,
+            "swish" => Self::Swish,
+            "linear" => Self::Linear,
+            "hardtan" => Self::Hardtan,
+            "logistic" => Self::Logistic,
+            "normalize_channels" => Self::NormChan,
+            "normalize_channels_softmax" => Self::NormChanSoftmax,
+            "normalize_channels_softmax_maxval" => Self::NormChanSoftmaxMaxval,
+            "hard_mish" => Self::HardMish,
+            "revleaky" => Self::RevLeaky,
+            _ => {
+                return Err(Error::ConversionError {
+                    reason: format!("unknown activation {:?}", name),
+                })
+            }
+        };
+        Ok(activation)
+    }
 }
 
 /// Binary activation types.
@@ -81,6 +257,39 @@ pub enum BinaryActivation {
     Mult = sys::BINARY_ACTIVATION_MULT as usize,
 }
 
+impl BinaryActivation {
+    /// Get the name used in config files.
+    ///
+    /// Binary activations cannot be declared in config files, so they get a lowercase name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Div => "div",
+            Self::Sub => "sub",
+            Self::Mult => "mult",
+        }
+    }
+}
+
+impl FromStr for BinaryActivation {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let kind = match name {
+            "add" => Self::Add,
+            "div" => Self::Div,
+            "sub" => Self::Sub,
+            "mult" => Self::Mult,
+            _ => {
+                return Err(Error::ConversionError {
+                    reason: format!("unknown binary activation {:?}", name),
+                })
+            }
+        };
+        Ok(kind)
+    }
+}
+
 /// Cost, or namely, loss types.
 #[repr(usize)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive)]
@@ -93,6 +302,41 @@ pub enum CostType {
     Smooth = sys::COST_TYPE_SMOOTH as usize,
 }
 
+impl CostType {
+    /// Get the name used in config files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::L1 => "l1",
+            Self::Seg => "seg",
+            Self::Sse => "sse",
+            Self::Wgan => "wgan",
+            Self::Masked => "masked",
+            Self::Smooth => "smooth",
+        }
+    }
+}
+
+impl FromStr for CostType {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let kind = match name {
+            "l1" => Self::L1,
+            "seg" => Self::Seg,
+            "sse" => Self::Sse,
+            "wgan" => Self::Wgan,
+            "masked" => Self::Masked,
+            "smooth" => Self::Smooth,
+            _ => {
+                return Err(Error::ConversionError {
+                    reason: format!("unknown cost type {:?}", name),
+                })
+            }
+        };
+        Ok(kind)
+    }
+}
+
 /// Weights format types.
 #[repr(usize)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive)]
@@ -102,6 +346,35 @@ pub enum WeightsType {
     PerFeature = sys::WEIGHTS_TYPE_T_PER_FEATURE as usize,
 }
 
+impl WeightsType {
+    /// Get the name used in config files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NoWeights => "none",
+            Self::PerChannel => "per_channel",
+            Self::PerFeature => "per_feature",
+        }
+    }
+}
+
+impl FromStr for WeightsType {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let kind = match name {
+            "none" => Self::NoWeights,
+            "per_channel" => Self::PerChannel,
+            "per_feature" | "per_layer" => Self::PerFeature,
+            _ => {
+                return Err(Error::ConversionError {
+                    reason: format!("unknown weights type {:?}", name),
+                })
+            }
+        };
+        Ok(kind)
+    }
+}
+
 /// Weights normalization types.
 #[repr(usize)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive)]
@@ -111,6 +384,35 @@ pub enum WeightsNormalizion {
     Softmax = sys::WEIGHTS_NORMALIZATION_T_SOFTMAX_NORMALIZATION as usize,
 }
 
+impl WeightsNormalizion {
+    /// Get the name used in config files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Relu => "relu",
+            Self::Softmax => "softmax",
+        }
+    }
+}
+
+impl FromStr for WeightsNormalizion {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let kind = match name {
+            "none" => Self::None,
+            "relu" | "avg_relu" => Self::Relu,
+            "softmax" => Self::Softmax,
+            _ => {
+                return Err(Error::ConversionError {
+                    reason: format!("unknown weights normalization {:?}", name),
+                })
+            }
+        };
+        Ok(kind)
+    }
+}
+
 /// Non-maximum suppression (NMS) types.
 #[repr(usize)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive)]
@@ -121,6 +423,37 @@ pub enum NmsKind {
     Default = sys::NMS_KIND_DEFAULT_NMS as usize,
 }
 
+impl NmsKind {
+    /// Get the name used in config files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Diou => "diounms",
+            Self::Greedy => "greedynms",
+            Self::Corners => "cornersnms",
+            Self::Default => "default",
+        }
+    }
+}
+
+impl FromStr for NmsKind {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let kind = match name {
+            "diounms" => Self::Diou,
+            "greedynms" => Self::Greedy,
+            "cornersnms" => Self::Corners,
+            "default" => Self::Default,
+            _ => {
+                return Err(Error::ConversionError {
+                    reason: format!("unknown NMS kind {:?}", name),
+                })
+            }
+        };
+        Ok(kind)
+    }
+}
+
 /// IoU loss types.
 #[repr(usize)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive)]
@@ -132,6 +465,39 @@ pub enum IoULoss {
     GIoU = sys::IOU_LOSS_GIOU as usize,
 }
 
+impl IoULoss {
+    /// Get the name used in config files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::IoU => "iou",
+            Self::Mse => "mse",
+            Self::CIoU => "ciou",
+            Self::DIoU => "diou",
+            Self::GIoU => "giou",
+        }
+    }
+}
+
+impl FromStr for IoULoss {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let loss = match name {
+            "iou" => Self::IoU,
+            "mse" => Self::Mse,
+            "ciou" => Self::CIoU,
+            "diou" => Self::DIoU,
+            "giou" => Self::GIoU,
+            _ => {
+                return Err(Error::ConversionError {
+                    reason: format!("unknown IoU loss {:?}", name),
+                })
+            }
+        };
+        Ok(loss)
+    }
+}
+
 /// YOLO point types.
 #[repr(usize)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive)]
@@ -140,3 +506,90 @@ pub enum YoloPoint {
     LeftTop = sys::YOLO_POINT_YOLO_LEFT_TOP as usize,
     RightBottom = sys::YOLO_POINT_YOLO_RIGHT_BOTTOM as usize,
 }
+
+impl YoloPoint {
+    /// Get the name used in config files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Center => "center",
+            Self::LeftTop => "left_top",
+            Self::RightBottom => "right_bottom",
+        }
+    }
+}
+
+impl FromStr for YoloPoint {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let kind = match name {
+            "center" => Self::Center,
+            "left_top" => Self::LeftTop,
+            "right_bottom" => Self::RightBottom,
+            _ => {
+                return Err(Error::ConversionError {
+                    reason: format!("unknown YOLO point {:?}", name),
+                })
+            }
+        };
+        Ok(kind)
+    }
+}
+
+impl LayerType {
+    /// Parse a name returned by [LayerType::name], including the types that cannot be declared
+    /// in config files.
+    #[cfg(feature = "serde")]
+    fn from_name(name: &str) -> Result<Self, Error> {
+        let type_ = match name {
+            "blank" => Self::Blank,
+            "iseg" => Self::Iseg,
+            "xnor" => Self::Xnor,
+            "l2norm" => Self::L2Norm,
+            "logxent" => Self::Logxent,
+            "deconvolutional" => Self::Deconvolutional,
+            _ => return name.parse(),
+        };
+        Ok(type_)
+    }
+}
+
+/// Serialize the kinds by their names used in config files.
+#[cfg(feature = "serde")]
+macro_rules! impl_serde_by_name {
+    ($($kind:ty => $parse:path),* $(,)?) => {
+        $(
+            impl serde::Serialize for $kind {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: serde::Serializer,
+                {
+                    serializer.serialize_str(self.name())
+                }
+            }
+
+            impl<'de> serde::Deserialize<'de> for $kind {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    let name = String::deserialize(deserializer)?;
+                    $parse(&name).map_err(serde::de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "serde")]
+impl_serde_by_name! {
+    LayerType => LayerType::from_name,
+    Activation => Activation::from_str,
+    BinaryActivation => BinaryActivation::from_str,
+    CostType => CostType::from_str,
+    WeightsType => WeightsType::from_str,
+    WeightsNormalizion => WeightsNormalizion::from_str,
+    NmsKind => NmsKind::from_str,
+    IoULoss => IoULoss::from_str,
+    YoloPoint => YoloPoint::from_str,
+}
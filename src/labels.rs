@@ -0,0 +1,144 @@
+use crate::{error::Error, network::Network};
+use std::{
+    fs,
+    iter::FromIterator,
+    ops::Index,
+    path::{Path, PathBuf},
+    slice,
+};
+
+/// The label names of classes, indexed by class.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Labels {
+    labels: Vec<String>,
+}
+
+impl Labels {
+    /// Create labels from names ordered by class.
+    pub fn new(labels: Vec<String>) -> Self {
+        Self { labels }
+    }
+
+    /// Load a `.names` file with one label per line.
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| Error::LabelsOpenError {
+            path: path.to_owned(),
+            error,
+        })?;
+        Ok(text.lines().map(ToOwned::to_owned).collect())
+    }
+
+    /// Load the `.names` file given by the `names=` entry of a `.data` file, such as
+    /// `cfg/coco.data`.
+    ///
+    /// Like libdarknet, whitespaces in the file are ignored, and a relative `names` path is
+    /// resolved from the working directory, not from the `.data` file.
+    pub fn from_data_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| Error::LabelsOpenError {
+            path: path.to_owned(),
+            error,
+        })?;
+        let names = text
+            .lines()
+            .map(|line| line.split_whitespace().collect::<String>())
+            .filter(|line| !line.is_empty() && !line.starts_with(&['#', ';'][..]))
+            .filter_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                (key == "names").then(|| PathBuf::from(value))
+            })
+            .next()
+            .ok_or_else(|| Error::InvalidDataFileError {
+                reason: format!("{} has no names entry", path.display()),
+            })?;
+        Self::open(names)
+    }
+
+    /// Check that there is one label per class of the network.
+    ///
+    /// It returns an [Err] if the number of labels is not [Network::num_classes].
+    pub fn check(&self, network: &Network) -> Result<(), Error> {
+        let classes = network.num_classes();
+        if self.len() != classes {
+            return Err(Error::LabelCountError {
+                labels: self.len(),
+                classes,
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the label of a class.
+    pub fn get(&self, class: usize) -> Option<&str> {
+        self.labels.get(class).map(String::as_str)
+    }
+
+    /// Get the class index of a label.
+    pub fn position(&self, label: &str) -> Option<usize> {
+        self.labels.iter().position(|name| name == label)
+    }
+
+    /// Get the number of labels.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Returns `true` if there are no labels.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Get the iterator of labels in class order.
+    pub fn iter(&self) -> slice::Iter<'_, String> {
+        self.labels.iter()
+    }
+
+    /// Get the labels as a slice.
+    pub fn as_slice(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// Unwrap the labels.
+    pub fn into_vec(self) -> Vec<String> {
+        self.labels
+    }
+}
+
+impl Index<usize> for Labels {
+    type Output = str;
+
+    fn index(&self, class: usize) -> &Self::Output {
+        &self.labels[class]
+    }
+}
+
+impl From<Vec<String>> for Labels {
+    fn from(labels: Vec<String>) -> Self {
+        Self::new(labels)
+    }
+}
+
+impl FromIterator<String> for Labels {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = String>,
+    {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a Labels {
+    type Item = &'a String;
+    type IntoIter = slice::Iter<'a, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
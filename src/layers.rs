@@ -162,4 +162,46 @@ impl<'a> Layer<'a> {
             self.output_channels(),
         )
     }
+
+    /// Get the number of output values per batch item.
+    ///
+    /// Unlike [Layer::output_shape], it is also set for layers without a spatial shape, such as
+    /// `[softmax]` layers.
+    pub fn num_outputs(&self) -> usize {
+        self.layer.outputs as usize
+    }
+
+    /// Get the number of classes of a detection layer, such as a `[yolo]` layer.
+    pub fn classes(&self) -> usize {
+        self.layer.classes as usize
+    }
+
+    /// Get an owned summary of the type and shapes of the layer.
+    pub fn summary(&self) -> LayerSummary {
+        LayerSummary {
+            layer_type: self.type_(),
+            activation: self.activation(),
+            input_shape: self.input_shape(),
+            output_shape: self.output_shape(),
+            num_outputs: self.num_outputs(),
+        }
+    }
+}
+
+/// The type and shapes of a layer, which do not borrow the network.
+///
+/// Shapes are (width, height, channels) tuples as returned by [Layer].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerSummary {
+    /// The layer type.
+    pub layer_type: Option<LayerType>,
+    /// The activation type, as returned by [Layer::activation].
+    pub activation: Option<Activation>,
+    /// The input shape.
+    pub input_shape: (usize, usize, usize),
+    /// The output shape.
+    pub output_shape: (usize, usize, usize),
+    /// The number of output values per batch item.
+    pub num_outputs: usize,
 }
@@ -0,0 +1,162 @@
+use crate::bbox::BoundingBox;
+
+/// The transform from an image to the network input by letterboxing.
+///
+/// The image is resized to fit in the network input while keeping its ratio, and centered with
+/// gray padding, like [Image::letter_box](crate::Image::letter_box) and
+/// [Network::predict](crate::Network::predict) with `use_letter_box`. The sizes are rounded
+/// down to integers the same way as libdarknet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LetterBox {
+    /// The source image width.
+    pub image_width: usize,
+    /// The source image height.
+    pub image_height: usize,
+    /// The network input width.
+    pub net_width: usize,
+    /// The network input height.
+    pub net_height: usize,
+    /// The width of the resized image inside the network input.
+    pub resized_width: usize,
+    /// The height of the resized image inside the network input.
+    pub resized_height: usize,
+}
+
+impl LetterBox {
+    /// Compute the letterbox transform from an image to the network input size.
+    ///
+    /// An empty image, with a width or height of 0, has nothing to resize, so the network input
+    /// is all padding. See [LetterBox::is_empty].
+    pub fn new(
+        image_width: usize,
+        image_height: usize,
+        net_width: usize,
+        net_height: usize,
+    ) -> Self {
+        let (resized_width, resized_height) = if image_width == 0 || image_height == 0 {
+            (0, 0)
+        } else if (net_width as f32 / image_width as f32)
+            < (net_height as f32 / image_height as f32)
+        {
+            (net_width, image_height * net_width / image_width)
+        } else {
+            (image_width * net_height / image_height, net_height)
+        };
+        Self {
+            image_width,
+            image_height,
+            net_width,
+            net_height,
+            resized_width,
+            resized_height,
+        }
+    }
+
+    /// Get the transform of resizing an image to the network input without keeping the ratio,
+    /// which is used when letterboxing is disabled.
+    pub fn stretch(
+        image_width: usize,
+        image_height: usize,
+        net_width: usize,
+        net_height: usize,
+    ) -> Self {
+        Self {
+            image_width,
+            image_height,
+            net_width,
+            net_height,
+            resized_width: net_width,
+            resized_height: net_height,
+        }
+    }
+
+    /// Check if the source image is empty, with a width or height of 0.
+    ///
+    /// The transform of an empty image is degenerate: the scales are 0, and the boxes are
+    /// mapped to the empty box at the origin.
+    pub fn is_empty(&self) -> bool {
+        self.image_width == 0 || self.image_height == 0
+    }
+
+    /// Get the horizontal scale from image pixels to network input pixels.
+    pub fn scale_x(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        self.resized_width as f32 / self.image_width as f32
+    }
+
+    /// Get the vertical scale from image pixels to network input pixels.
+    pub fn scale_y(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        self.resized_height as f32 / self.image_height as f32
+    }
+
+    /// Get the padding on the left of the resized image, in network input pixels.
+    ///
+    /// It is not rounded, like the box correction of libdarknet, while
+    /// [Image::letter_box](crate::Image::letter_box) places the image at the rounded down offset.
+    /// It is negative if the resized image is wider than the network input.
+    pub fn pad_x(&self) -> f32 {
+        (self.net_width as f32 - self.resized_width as f32) / 2.0
+    }
+
+    /// Get the padding on the top of the resized image, in network input pixels.
+    ///
+    /// It is negative if the resized image is taller than the network input.
+    pub fn pad_y(&self) -> f32 {
+        (self.net_height as f32 - self.resized_height as f32) / 2.0
+    }
+
+    /// Map a box normalized by the network input size to a box normalized by the image size.
+    ///
+    /// It uses the same formula as `correct_yolo_boxes` in libdarknet.
+    pub fn to_image(&self, bbox: &BoundingBox) -> BoundingBox {
+        if self.is_empty() {
+            return BoundingBox::default();
+        }
+        let (net_w, net_h) = (self.net_width as f32, self.net_height as f32);
+        let ratio_w = self.resized_width as f32 / net_w;
+        let ratio_h = self.resized_height as f32 / net_h;
+        // libdarknet computes the center in double precision
+        let offset_x = self.pad_x() as f64 / net_w as f64;
+        let offset_y = self.pad_y() as f64 / net_h as f64;
+        BoundingBox {
+            x: ((bbox.x as f64 - offset_x) / ratio_w as f64) as f32,
+            y: ((bbox.y as f64 - offset_y) / ratio_h as f64) as f32,
+            w: bbox.w * (1.0 / ratio_w),
+            h: bbox.h * (1.0 / ratio_h),
+        }
+    }
+
+    /// Map a box normalized by the image size to a box normalized by the network input size.
+    ///
+    /// It is the inverse of [LetterBox::to_image].
+    pub fn to_network(&self, bbox: &BoundingBox) -> BoundingBox {
+        if self.is_empty() {
+            return BoundingBox::default();
+        }
+        let (net_w, net_h) = (self.net_width as f32, self.net_height as f32);
+        let ratio_w = self.resized_width as f32 / net_w;
+        let ratio_h = self.resized_height as f32 / net_h;
+        BoundingBox {
+            x: bbox.x * ratio_w + self.pad_x() / net_w,
+            y: bbox.y * ratio_h + self.pad_y() / net_h,
+            w: bbox.w * ratio_w,
+            h: bbox.h * ratio_h,
+        }
+    }
+
+    /// Map a box normalized by the network input size to image pixel coordinates.
+    pub fn to_image_pixels(&self, bbox: &BoundingBox) -> BoundingBox {
+        self.to_image(bbox)
+            .to_pixels(self.image_width, self.image_height)
+    }
+
+    /// Map a box in image pixel coordinates to a box normalized by the network input size.
+    pub fn from_image_pixels(&self, bbox: &BoundingBox) -> BoundingBox {
+        self.to_network(&bbox.to_normalized(self.image_width, self.image_height))
+    }
+}
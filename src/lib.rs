@@ -1,21 +1,43 @@
+pub mod cfg;
+pub mod eval;
+pub mod export;
+pub mod nms;
+pub mod track;
+pub mod tta;
+pub mod weights;
+
+mod bbox;
+mod classifier;
 mod detections;
+mod draw;
 mod error;
 mod image;
 mod kinds;
+mod labels;
 mod layers;
+mod letterbox;
 mod network;
+mod predict;
 mod train;
 mod utils;
 
 pub use crate::image::{Image, IntoCowImage};
-pub use detections::{Detection, Detections, DetectionsIter};
+#[cfg(feature = "serde")]
+pub use bbox::BBoxDef;
+pub use bbox::BoundingBox;
+pub use classifier::{Classification, Classifier};
+pub use detections::{Detection, Detections, DetectionsIter, OwnedDetection};
+pub use draw::class_color;
 pub use error::Error;
 pub use kinds::{
     Activation, BinaryActivation, CostType, IoULoss, LayerType, NmsKind, WeightsNormalizion,
     WeightsType, YoloPoint,
 };
-pub use layers::{Layer, Layers, LayersIter};
+pub use labels::Labels;
+pub use layers::{Layer, LayerSummary, Layers, LayersIter};
+pub use letterbox::LetterBox;
 pub use network::Network;
+pub use predict::{PredictOptions, TileOptions};
 pub use train::train_detector;
 
 /// Bounding box in cxcywh format.
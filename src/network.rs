@@ -1,45 +1,120 @@
 use crate::{
-    detections::Detections,
+    bbox::BoundingBox,
+    cfg,
+    detections::{Detections, OwnedDetection},
     error::Error,
     image::IntoCowImage,
+    kinds::{LayerType, NmsKind},
     layers::{Layer, Layers},
-    utils,
+    letterbox::LetterBox,
+    nms,
+    predict::{PredictOptions, TileOptions},
+    tta::{Augmentation, TtaOptions},
+    utils::{self, MemFile},
+    weights,
 };
 use darknet_sys as sys;
 
 use std::{
+    cmp::Ordering,
     ffi::c_void,
-    os::raw::c_int,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    mem,
+    os::raw::{c_char, c_int},
     path::Path,
     ptr::{self, NonNull},
     slice,
 };
 
+extern "C" {
+    // exported by libdarknet, which calls it before single image predictions
+    fn set_batch_network(net: *mut sys::network, b: c_int);
+    // exported by libdarknet, and used by load_network_custom
+    fn parse_network_cfg_custom(
+        filename: *mut c_char,
+        batch: c_int,
+        time_steps: c_int,
+    ) -> sys::network;
+    fn load_weights(net: *mut sys::network, filename: *mut c_char);
+}
+
 /// The network wrapper type for Darknet.
 pub struct Network {
     net: NonNull<sys::network>,
+    /// The batch size the layer buffers are allocated for.
+    max_batch: usize,
 }
 
 impl Network {
     /// Build the network instance from a configuration file and an optional weights file.
     ///
-    /// This will abort the program with an exit code of 1 if any of the following occur.
+    /// The files are checked before they are passed to libdarknet, which would otherwise
+    /// abort the program with an exit code of 1. It returns an [Err] if any of the following occur.
+    /// - \[cfg\] or \[weights\] (if provided) contain a null byte.
+    /// - \[cfg\] cannot be read.
     /// - The config has no sections.
     /// - The first section of the config is not `[net]` or `[network]`.
-    /// - `fopen` fails on \[weights\] (if provided).
-    /// - The weights file is invalid
+    /// - \[weights\] (if provided) cannot be opened.
+    /// - The weights file has a truncated header or a partial float at the end.
+    /// - The weights file ends inside a layer. This is only checked if the config parses as a
+    ///   [Config](cfg::Config) with a supported layout. Like libdarknet, data after the last
+    ///   layer is ignored.
     ///
-    /// Returns an [Err] if [cfg] or \[weights\] (if provided) contain a null byte.
+    /// Errors in the layer sections are not checked and may still abort the program.
     pub fn load<C, W>(cfg: C, weights: Option<W>, clear: bool) -> Result<Network, Error>
     where
         C: AsRef<Path>,
         W: AsRef<Path>,
     {
-        let weights_cstr = weights
-            .map(|path| utils::path_to_cstring_or_error(path.as_ref()))
+        Self::load_impl(
+            cfg.as_ref(),
+            weights.as_ref().map(AsRef::as_ref),
+            clear,
+            None,
+        )
+    }
+
+    /// Build the network instance like [Network::load], with buffers for \[batch\] images
+    /// regardless of the `batch` and `subdivisions` options of the config.
+    ///
+    /// Use it to run [Network::predict_batch] on batches of that size. Unlike
+    /// `load_network_custom` in libdarknet, the batch normalization is not fused into the
+    /// convolutions, so the weights can still be saved and reloaded.
+    pub fn load_with_batch<C, W>(
+        cfg: C,
+        weights: Option<W>,
+        clear: bool,
+        batch: usize,
+    ) -> Result<Network, Error>
+    where
+        C: AsRef<Path>,
+        W: AsRef<Path>,
+    {
+        Self::load_impl(
+            cfg.as_ref(),
+            weights.as_ref().map(AsRef::as_ref),
+            clear,
+            Some(batch.max(1)),
+        )
+    }
+
+    fn load_impl(
+        cfg_path: &Path,
+        weights_path: Option<&Path>,
+        clear: bool,
+        batch: Option<usize>,
+    ) -> Result<Network, Error> {
+        let weights_cstr = weights_path
+            .map(utils::path_to_cstring_or_error)
             .transpose()?;
 
-        let cfg_cstr = utils::path_to_cstring_or_error(cfg.as_ref())?;
+        let cfg_cstr = utils::path_to_cstring_or_error(cfg_path)?;
+
+        let config = cfg::check_file(cfg_path)?;
+        if let Some(path) = weights_path {
+            weights::check_file(path, config.as_ref())?;
+        }
 
         let clear = c_int::from(clear);
 
@@ -48,7 +123,10 @@ impl Network {
                 .as_ref()
                 .map_or(ptr::null_mut(), |cstr| cstr.as_ptr() as *mut _);
             let raw_cfg = cfg_cstr.as_ptr() as *mut _;
-            sys::load_network(raw_cfg, raw_weights, clear)
+            match batch {
+                Some(batch) => load_network_custom(raw_cfg, raw_weights, clear, batch as c_int),
+                None => sys::load_network(raw_cfg, raw_weights, clear),
+            }
         };
 
         let net = NonNull::new(ptr).ok_or_else(|| Error::InternalError {
@@ -59,7 +137,114 @@ impl Network {
         drop(cfg_cstr);
         drop(weights_cstr);
 
-        Ok(Self { net })
+        let max_batch = unsafe { net.as_ref().batch.max(1) as usize };
+        Ok(Self { net, max_batch })
+    }
+
+    /// Build the network instance from config text and an optional weights file.
+    ///
+    /// The text is checked and passed to libdarknet the same way as [Network::load].
+    /// An edited [Config](crate::cfg::Config) can be loaded with `config.to_string()`.
+    ///
+    /// On Linux the text is passed through an anonymous memory file. On other platforms it is
+    /// written to a temporary file that only the current user can read, which is removed
+    /// after loading.
+    pub fn from_cfg_str<W>(cfg: &str, weights: Option<W>, clear: bool) -> Result<Network, Error>
+    where
+        W: AsRef<Path>,
+    {
+        let cfg_file = MemFile::new_or_temp(cfg.as_bytes())?;
+        Self::load(cfg_file.path(), weights, clear)
+    }
+
+    /// Build the network instance from in-memory config and weights buffers.
+    ///
+    /// The buffers are checked the same way as [Network::load], and passed to libdarknet
+    /// through anonymous memory files, so they never touch the filesystem.
+    ///
+    /// Only Linux has anonymous memory files. On other platforms it returns
+    /// [Error::UnsupportedError] instead of writing the buffers to disk.
+    pub fn from_bytes(cfg: &[u8], weights: Option<&[u8]>, clear: bool) -> Result<Network, Error> {
+        let cfg_file = MemFile::new(cfg)?;
+        let weights_file = weights.map(MemFile::new).transpose()?;
+        Self::load(
+            cfg_file.path(),
+            weights_file.as_ref().map(MemFile::path),
+            clear,
+        )
+    }
+
+    /// Build the network instance by reading the config and weights from readers.
+    ///
+    /// See [Network::from_bytes] for details.
+    pub fn from_reader<C, W>(mut cfg: C, weights: Option<W>, clear: bool) -> Result<Network, Error>
+    where
+        C: Read,
+        W: Read,
+    {
+        let mut cfg_buf = vec![];
+        cfg.read_to_end(&mut cfg_buf)?;
+        let weights_buf = weights
+            .map(|mut reader| -> Result<_, Error> {
+                let mut buf = vec![];
+                reader.read_to_end(&mut buf)?;
+                Ok(buf)
+            })
+            .transpose()?;
+        Self::from_bytes(&cfg_buf, weights_buf.as_deref(), clear)
+    }
+
+    /// Load weights into the first \[cutoff\] layers from a file, like libdarknet does for
+    /// partial weights such as `yolov4.conv.137`. Returns the number of layers visited.
+    ///
+    /// Loading stops early if the file ends on a layer boundary. It returns an [Err] if the file
+    /// ends inside a layer, in which case the earlier layers are already updated.
+    pub fn load_weights_upto<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        cutoff: usize,
+    ) -> Result<usize, Error> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|error| Error::WeightsOpenError {
+            path: path.to_owned(),
+            error,
+        })?;
+        self.read_weights_upto(BufReader::new(file), cutoff)
+    }
+
+    /// Load weights into the first \[cutoff\] layers from a reader.
+    ///
+    /// See [Network::load_weights_upto] for details.
+    pub fn read_weights_upto<R: Read>(&mut self, reader: R, cutoff: usize) -> Result<usize, Error> {
+        weights::read_network(unsafe { self.net.as_mut() }, cutoff, reader)
+    }
+
+    /// Save the weights of all layers to a file in the upstream darknet format.
+    pub fn save_weights<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.save_weights_upto(path, self.num_layers())
+    }
+
+    /// Save the weights of the first \[cutoff\] layers to a file, like `darknet partial` does.
+    pub fn save_weights_upto<P: AsRef<Path>>(&self, path: P, cutoff: usize) -> Result<(), Error> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|error| Error::WeightsCreateError {
+            path: path.to_owned(),
+            error,
+        })?;
+        self.write_weights_upto(BufWriter::new(file), cutoff)
+    }
+
+    /// Write the weights of all layers to a writer in the upstream darknet format.
+    pub fn write_weights<W: Write>(&self, writer: W) -> Result<(), Error> {
+        self.write_weights_upto(writer, self.num_layers())
+    }
+
+    /// Write the weights of the first \[cutoff\] layers to a writer.
+    ///
+    /// Like libdarknet, the seen images counter is recomputed from the current iteration, so it
+    /// is rounded down to whole batches.
+    pub fn write_weights_upto<W: Write>(&self, writer: W, cutoff: usize) -> Result<(), Error> {
+        weights::write_network(unsafe { self.net.as_ref() }, cutoff, writer)
     }
 
     /// Get network input width.
@@ -109,7 +294,73 @@ impl Network {
         }
     }
 
+    /// Get the layer whose output is returned by [Network::forward], which is the last layer
+    /// that is not a `[cost]` layer.
+    pub fn output_layer(&self) -> Layer<'_> {
+        let index = (1..self.num_layers())
+            .rev()
+            .find(|&index| {
+                let layer = self.get_layer(index).unwrap();
+                layer.type_() != Some(LayerType::Cost)
+            })
+            .unwrap_or(0);
+        self.get_layer(index).unwrap()
+    }
+
+    /// Get the number of classes predicted by the network.
+    ///
+    /// It is the `classes` of the output layer if it is a detection layer, such as `[yolo]`, or
+    /// the number of output values otherwise, such as for classifiers.
+    pub fn num_classes(&self) -> usize {
+        let layer = self.output_layer();
+        match layer.type_() {
+            Some(
+                LayerType::Yolo
+                | LayerType::GaussianYolo
+                | LayerType::Region
+                | LayerType::Detection,
+            ) => layer.classes(),
+            _ => layer.num_outputs(),
+        }
+    }
+
+    /// Run the network on raw input values and return the output of [Network::output_layer].
+    ///
+    /// The input is laid out like [Image](crate::Image) data, channel by channel, and must have as many values
+    /// as the product of [Network::input_shape]. No post-processing is applied, which suits
+    /// classifiers, embedding models and custom heads.
+    pub fn forward(&mut self, input: &[f32]) -> Result<&[f32], Error> {
+        let (channels, height, width) = self.input_shape();
+        let input_len = channels * height * width;
+        if input.len() != input_len {
+            return Err(Error::InputSizeError {
+                expected: input_len,
+                found: input.len(),
+            });
+        }
+
+        // libdarknet reads a whole batch, so the input is padded for networks loaded with batch > 1
+        let batch = unsafe { self.net.as_ref().batch.max(1) as usize };
+        let mut buffer = vec![0.0; input_len * batch];
+        buffer[..input_len].copy_from_slice(input);
+
+        let output_len = self.output_layer().num_outputs();
+        unsafe {
+            let output = sys::network_predict_ptr(self.net.as_ptr(), buffer.as_mut_ptr());
+            if output.is_null() {
+                return Err(Error::InternalError {
+                    reason: "the network has no output".into(),
+                });
+            }
+            Ok(slice::from_raw_parts(output, output_len))
+        }
+    }
+
     /// Run inference on an image.
+    ///
+    /// The boxes are normalized by the size of the image, whether or not it is letterboxed.
+    /// [LetterBox] maps them to and from the network input. See [Network::predict_with] for
+    /// named options.
     pub fn predict<'a, M>(
         &mut self,
         image: M,
@@ -118,25 +369,37 @@ impl Network {
         nms: f32,
         use_letter_box: bool,
     ) -> Detections
+    where
+        M: IntoCowImage<'a>,
+    {
+        let options = PredictOptions {
+            thresh,
+            hier_thresh: hier_thres,
+            nms,
+            letter_box: use_letter_box,
+            ..PredictOptions::default()
+        };
+        self.predict_with(image, &options)
+    }
+
+    /// Run inference on an image with the given options.
+    ///
+    /// Like libdarknet, the detections suppressed by NMS are kept with zero probabilities.
+    /// If [PredictOptions::classes] or [PredictOptions::max_detections] is set, the detections
+    /// without an allowed class above the threshold are removed, and the rest are sorted by
+    /// their best allowed class probability in descending order.
+    pub fn predict_with<'a, M>(&mut self, image: M, options: &PredictOptions) -> Detections
     where
         M: IntoCowImage<'a>,
     {
         let cow = image.into_cow_image();
 
         unsafe {
-            let output_layer = self
-                .net
-                .as_ref()
-                .layers
-                .add(self.num_layers() - 1)
-                .as_ref()
-                .unwrap();
-
             // run prediction
-            if use_letter_box {
-                sys::network_predict_image_letterbox(self.net.as_ptr(), cow.image);
+            if options.letter_box {
+                sys::network_predict_image_letterbox(self.net.as_ptr(), *cow.as_raw());
             } else {
-                sys::network_predict_image(self.net.as_ptr(), cow.image);
+                sys::network_predict_image(self.net.as_ptr(), *cow.as_raw());
             }
 
             let mut nboxes: c_int = 0;
@@ -144,37 +407,392 @@ impl Network {
                 self.net.as_mut(),
                 cow.width() as c_int,
                 cow.height() as c_int,
-                thresh,
-                hier_thres,
+                options.thresh,
+                options.hier_thresh,
                 ptr::null_mut(),
-                1,
+                options.relative as c_int,
                 &mut nboxes,
-                use_letter_box as c_int,
+                options.letter_box as c_int,
             );
             let dets = NonNull::new(dets_ptr).unwrap();
+            self.sort_nms(dets, nboxes, options.nms, options.nms_kind);
+
+            let mut detections = Detections {
+                detections: dets,
+                n_detections: nboxes as usize,
+                n_allocated: nboxes as usize,
+            };
+            if options.filters() {
+                filter_detections(&mut detections, options);
+            }
+            detections
+        }
+    }
+
+    /// Run inference on overlapping tiles of a large image, and merge the detections.
+    ///
+    /// Each tile is cropped from the image and predicted with [TileOptions::predict], and its
+    /// boxes are moved to the coordinates of the whole image. With [TileOptions::full_image],
+    /// the whole image is predicted as well to find the objects larger than a tile. The
+    /// detections are then merged by [TileOptions::nms], and the ones without a class
+    /// probability left are removed. They are returned in descending order of their best class
+    /// probability, up to [PredictOptions::max_detections].
+    pub fn predict_tiled<'a, M>(&mut self, image: M, options: &TileOptions) -> Vec<OwnedDetection>
+    where
+        M: IntoCowImage<'a>,
+    {
+        let image = image.into_cow_image();
+        let (width, height) = (image.width(), image.height());
+        let tile_width = options.tile_width.unwrap_or_else(|| self.input_width());
+        let tile_height = options.tile_height.unwrap_or_else(|| self.input_height());
+        let (tile_width, tile_height) = (tile_width.min(width), tile_height.min(height));
+        // boxes are normalized by the tile and mapped to the whole image, and only the merged
+        // detections are limited
+        let predict = PredictOptions {
+            relative: true,
+            max_detections: None,
+            ..options.predict.clone()
+        };
+
+        let mut detections = vec![];
+        for top in options.offsets(height, tile_height) {
+            for left in options.offsets(width, tile_width) {
+                let tile = image.crop(left as isize, top as isize, tile_width, tile_height);
+                let tile_detections = self.predict_with(&tile, &predict);
+                detections.extend(tile_detections.iter().map(|detection| {
+                    let mut detection = OwnedDetection::from(detection);
+                    let bbox = detection.bounding_box();
+                    detection.bbox = BoundingBox::new(
+                        (left as f32 + bbox.x * tile_width as f32) / width as f32,
+                        (top as f32 + bbox.y * tile_height as f32) / height as f32,
+                        bbox.w * tile_width as f32 / width as f32,
+                        bbox.h * tile_height as f32 / height as f32,
+                    )
+                    .into();
+                    detection
+                }));
+            }
+        }
+        if options.full_image {
+            let full_detections = self.predict_with(&*image, &predict);
+            detections.extend(full_detections.iter().map(OwnedDetection::from));
+        }
+
+        match &options.nms {
+            Some(nms_options) => nms::nms(&mut detections, nms_options),
+            None => detections.sort_by(|a, b| {
+                let score = |detection: &OwnedDetection| {
+                    detection.best_class(None).map_or(0.0, |(_, prob)| prob)
+                };
+                score(b).partial_cmp(&score(a)).unwrap_or(Ordering::Equal)
+            }),
+        }
+        detections.retain(|detection| detection.best_class(Some(0.0)).is_some());
+        if let Some(max_detections) = options.predict.max_detections {
+            detections.truncate(max_detections);
+        }
+        if !options.predict.relative {
+            for detection in &mut detections {
+                detection.bbox = detection.bounding_box().to_pixels(width, height).into();
+            }
+        }
+        detections
+    }
+
+    /// Run inference on augmentations of an image, and merge the detections.
+    ///
+    /// Each of [TtaOptions::augmentations] is predicted with [TtaOptions::predict], and its
+    /// boxes are mapped back to the image. The detections are then merged by
+    /// [TtaOptions::merge], and returned in descending order of their best class probability,
+    /// up to [PredictOptions::max_detections].
+    pub fn predict_tta<'a, M>(&mut self, image: M, options: &TtaOptions) -> Vec<OwnedDetection>
+    where
+        M: IntoCowImage<'a>,
+    {
+        let image = image.into_cow_image();
+        let (width, height) = (image.width(), image.height());
+        let predict = PredictOptions {
+            relative: true,
+            max_detections: None,
+            ..options.predict.clone()
+        };
 
-            // NMS sort
-            if nms != 0.0 {
-                if output_layer.nms_kind == sys::NMS_KIND_DEFAULT_NMS {
-                    sys::do_nms_sort(dets.as_ptr(), nboxes, output_layer.classes, nms);
+        let augmented: Vec<Vec<OwnedDetection>> = options
+            .augmentations
+            .iter()
+            .map(|augmentation| {
+                let detections = if *augmentation == Augmentation::IDENTITY {
+                    self.predict_with(&*image, &predict)
                 } else {
-                    sys::diounms_sort(
-                        dets.as_ptr(),
-                        nboxes,
-                        output_layer.classes,
-                        nms,
-                        output_layer.nms_kind,
-                        output_layer.beta_nms,
-                    );
+                    self.predict_with(augmentation.apply(&image), &predict)
+                };
+                detections
+                    .iter()
+                    .map(OwnedDetection::from)
+                    .filter(|detection| detection.best_class(Some(0.0)).is_some())
+                    .map(|mut detection| {
+                        let bbox = augmentation.to_image(&detection.bounding_box(), width, height);
+                        detection.bbox = bbox.into();
+                        detection
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut detections = options.merge.merge(&augmented);
+        if let Some(max_detections) = options.predict.max_detections {
+            detections.truncate(max_detections);
+        }
+        if !options.predict.relative {
+            for detection in &mut detections {
+                detection.bbox = detection.bounding_box().to_pixels(width, height).into();
+            }
+        }
+        detections
+    }
+
+    /// Get the largest batch size accepted by [Network::predict_batch] in one forward pass.
+    pub fn max_batch(&self) -> usize {
+        self.max_batch
+    }
+
+    /// Run inference on several images, packing up to [Network::max_batch] of them into each
+    /// forward pass, and return the detections of each image in the input order.
+    ///
+    /// The parameters and box coordinates are the same as [Network::predict]. Load the network
+    /// with [Network::load_with_batch] to choose the batch size. It returns an [Err] if the
+    /// detection heads are not `[yolo]` layers, or an image has a different number of channels
+    /// than the network input. See [Network::predict_batch_with] for named options.
+    pub fn predict_batch<'a, M>(
+        &mut self,
+        images: &'a [M],
+        thresh: f32,
+        hier_thres: f32,
+        nms: f32,
+        use_letter_box: bool,
+    ) -> Result<Vec<Detections>, Error>
+    where
+        &'a M: IntoCowImage<'a>,
+    {
+        let options = PredictOptions {
+            thresh,
+            hier_thresh: hier_thres,
+            nms,
+            letter_box: use_letter_box,
+            ..PredictOptions::default()
+        };
+        self.predict_batch_with(images, &options)
+    }
+
+    /// Run inference on several images with the given options, like [Network::predict_batch].
+    ///
+    /// The detections of each image are the same as [Network::predict_with] gives.
+    pub fn predict_batch_with<'a, M>(
+        &mut self,
+        images: &'a [M],
+        options: &PredictOptions,
+    ) -> Result<Vec<Detections>, Error>
+    where
+        &'a M: IntoCowImage<'a>,
+    {
+        if let Some(layer) = self.layers().iter().find(|layer| {
+            matches!(
+                layer.type_(),
+                Some(LayerType::Region | LayerType::Detection | LayerType::GaussianYolo)
+            )
+        }) {
+            return Err(Error::InvalidConfigError {
+                reason: format!(
+                    "batched detection supports [yolo] heads only, but found {:?}",
+                    layer.type_().unwrap()
+                ),
+            });
+        }
+
+        let (channels, height, width) = self.input_shape();
+        let input_len = channels * height * width;
+        let mut results = Vec::with_capacity(images.len());
+
+        for chunk in images.chunks(self.max_batch) {
+            let mut buffer = vec![0.0; input_len * chunk.len()];
+            let mut image_sizes = Vec::with_capacity(chunk.len());
+            for (image, input) in chunk.iter().zip(buffer.chunks_exact_mut(input_len)) {
+                let image = image.into_cow_image();
+                let resized = if options.letter_box {
+                    image.letter_box(width, height)
+                } else {
+                    image.resize(width, height)
+                };
+                if resized.get_data().len() != input_len {
+                    return Err(Error::InputSizeError {
+                        expected: input_len,
+                        found: resized.get_data().len(),
+                    });
                 }
+                input.copy_from_slice(resized.get_data());
+                image_sizes.push((image.width(), image.height()));
             }
 
-            Detections {
-                detections: dets,
-                n_detections: nboxes as usize,
+            unsafe {
+                set_batch_network(self.net.as_ptr(), chunk.len() as c_int);
+                let input = sys::image {
+                    w: width as c_int,
+                    h: height as c_int,
+                    c: channels as c_int,
+                    data: buffer.as_mut_ptr(),
+                };
+                // boxes are relative to the network input, and mapped back to each image below
+                let pairs = sys::network_predict_batch(
+                    self.net.as_ptr(),
+                    input,
+                    chunk.len() as c_int,
+                    width as c_int,
+                    height as c_int,
+                    options.thresh,
+                    options.hier_thresh,
+                    ptr::null_mut(),
+                    1,
+                    options.letter_box as c_int,
+                );
+                let pairs = NonNull::new(pairs).ok_or_else(|| Error::InternalError {
+                    reason: "failed to allocate batch detections".into(),
+                })?;
+
+                for (index, &(image_width, image_height)) in image_sizes.iter().enumerate() {
+                    let pair = *pairs.as_ptr().add(index);
+                    let dets = NonNull::new(pair.dets).unwrap();
+                    let transform = LetterBox::new(image_width, image_height, width, height);
+                    for det in slice::from_raw_parts_mut(dets.as_ptr(), pair.num as usize) {
+                        let mut bbox = BoundingBox::from(det.bbox);
+                        if options.letter_box {
+                            bbox = transform.to_image(&bbox);
+                        }
+                        if !options.relative {
+                            bbox = bbox.to_pixels(image_width, image_height);
+                        }
+                        det.bbox = bbox.into();
+                    }
+                    self.sort_nms(dets, pair.num, options.nms, options.nms_kind);
+                    let mut detections = Detections {
+                        detections: dets,
+                        n_detections: pair.num as usize,
+                        n_allocated: pair.num as usize,
+                    };
+                    if options.filters() {
+                        filter_detections(&mut detections, options);
+                    }
+                    results.push(detections);
+                }
+                libc::free(pairs.as_ptr() as *mut c_void);
             }
         }
+
+        Ok(results)
     }
+
+    /// Sort detections and suppress overlaps with the NMS kind of the output layer.
+    unsafe fn sort_nms(
+        &self,
+        dets: NonNull<sys::detection>,
+        nboxes: c_int,
+        nms: f32,
+        nms_kind: Option<NmsKind>,
+    ) {
+        if nms == 0.0 {
+            return;
+        }
+        let output_layer = self
+            .net
+            .as_ref()
+            .layers
+            .add(self.num_layers() - 1)
+            .as_ref()
+            .unwrap();
+
+        let nms_kind = nms_kind.map_or(output_layer.nms_kind, |kind| kind as sys::NMS_KIND);
+        if nms_kind == sys::NMS_KIND_DEFAULT_NMS {
+            sys::do_nms_sort(dets.as_ptr(), nboxes, output_layer.classes, nms);
+        } else {
+            sys::diounms_sort(
+                dets.as_ptr(),
+                nboxes,
+                output_layer.classes,
+                nms,
+                nms_kind,
+                output_layer.beta_nms,
+            );
+        }
+    }
+}
+
+/// Build a network with a batch size like `load_network_custom` in libdarknet, but without fusing
+/// the batch normalization into the convolutions, so that the layers keep the layout of the
+/// config for weights I/O.
+unsafe fn load_network_custom(
+    cfg: *mut c_char,
+    weights: *mut c_char,
+    clear: c_int,
+    batch: c_int,
+) -> *mut sys::network {
+    // allocated by calloc like libdarknet, and freed in the same way on drop
+    let net = libc::calloc(1, mem::size_of::<sys::network>()) as *mut sys::network;
+    if net.is_null() {
+        return net;
+    }
+    ptr::write(net, parse_network_cfg_custom(cfg, batch, 1));
+    if !weights.is_null() {
+        load_weights(net, weights);
+    }
+    if clear != 0 {
+        *(*net).seen = 0;
+        *(*net).cur_iteration = 0;
+    }
+    net
+}
+
+/// Keep the detections with an allowed class above the threshold, sorted by the best allowed
+/// class probability, and move the others past the end so that they are still freed.
+fn filter_detections(detections: &mut Detections, options: &PredictOptions) {
+    let dets = unsafe {
+        slice::from_raw_parts_mut(detections.detections.as_ptr(), detections.n_allocated)
+    };
+    let score = |det: &sys::detection| {
+        let probabilities = unsafe { slice::from_raw_parts(det.prob, det.classes as usize) };
+        let allowed = |class: &usize| match &options.classes {
+            Some(classes) => classes.contains(class),
+            None => true,
+        };
+        probabilities
+            .iter()
+            .enumerate()
+            .filter(|(class, &prob)| allowed(class) && prob >= options.thresh && prob > 0.0)
+            .map(|(_, &prob)| prob)
+            .fold(None, |best: Option<f32>, prob| {
+                Some(best.map_or(prob, |best| best.max(prob)))
+            })
+    };
+
+    let mut kept: Vec<(f32, sys::detection)> = vec![];
+    let mut removed: Vec<sys::detection> = vec![];
+    for det in dets.iter() {
+        match score(det) {
+            Some(score) => kept.push((score, *det)),
+            None => removed.push(*det),
+        }
+    }
+    kept.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+    if let Some(max_detections) = options.max_detections {
+        removed.extend(
+            kept.drain(max_detections.min(kept.len())..)
+                .map(|(_, det)| det),
+        );
+    }
+
+    detections.n_detections = kept.len();
+    let reordered = kept.into_iter().map(|(_, det)| det).chain(removed);
+    dets.iter_mut()
+        .zip(reordered)
+        .for_each(|(det, reordered)| *det = reordered);
 }
 
 impl Drop for Network {
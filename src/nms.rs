@@ -0,0 +1,279 @@
+//! Non-maximum suppression (NMS) and box fusion over [OwnedDetection]s.
+//!
+//! Run [Network::predict](crate::Network::predict) with `nms = 0` to get the raw detections,
+//! then suppress the overlaps with [nms], or fuse the detections of several models with
+//! [weighted_box_fusion].
+
+use crate::{bbox::BoundingBox, detections::OwnedDetection, kinds::NmsKind, BBox};
+use std::cmp::Ordering;
+
+/// The way a box suppresses the boxes it overlaps with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Suppression {
+    /// Remove the boxes whose IoU is above the threshold, like `do_nms_sort` in libdarknet.
+    Greedy,
+    /// Remove the boxes whose IoU minus the center distance term, raised to the power of
+    /// \[beta\], is above the threshold, like `diounms_sort` in libdarknet.
+    Diou {
+        /// The exponent of the distance term.
+        beta: f32,
+    },
+    /// Multiply the scores of the boxes whose IoU is above the threshold by (1 - IoU).
+    SoftLinear,
+    /// Multiply the scores of all overlapping boxes by exp(-IoU² / \[sigma\]).
+    SoftGaussian {
+        /// The spread of the decay.
+        sigma: f32,
+    },
+}
+
+impl Suppression {
+    /// Get the suppression used by libdarknet for the NMS kind and `beta_nms` of an output
+    /// layer.
+    pub fn from_nms_kind(kind: NmsKind, beta: f32) -> Self {
+        match kind {
+            NmsKind::Default | NmsKind::Corners => Self::Greedy,
+            // box_diou in libdarknet raises the distance term to the power of 0.6
+            NmsKind::Greedy => Self::Diou { beta: 0.6 },
+            NmsKind::Diou => Self::Diou { beta },
+        }
+    }
+
+    /// Get the factor to multiply the score of \[other\] by.
+    fn decay(&self, kept: &BoundingBox, other: &BoundingBox, iou_threshold: f32) -> f32 {
+        let iou = kept.iou(other);
+        let suppressed = match *self {
+            Self::Greedy => iou > iou_threshold,
+            Self::Diou { beta } => {
+                let distance = kept
+                    .center_distance_ratio(other)
+                    .map_or(0.0, |ratio| ratio.powf(beta));
+                iou - distance > iou_threshold
+            }
+            Self::SoftLinear => return if iou > iou_threshold { 1.0 - iou } else { 1.0 },
+            Self::SoftGaussian { sigma } => return (-iou * iou / sigma).exp(),
+        };
+        if suppressed {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+/// The options of [nms].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NmsOptions {
+    /// The suppression method.
+    pub suppression: Suppression,
+    /// The IoU threshold, which is not used by [Suppression::SoftGaussian].
+    pub iou_threshold: f32,
+    /// Suppress boxes across classes by their best class score, instead of class by class.
+    pub class_agnostic: bool,
+    /// The scores decayed below the threshold are set to zero.
+    pub score_threshold: f32,
+}
+
+impl Default for NmsOptions {
+    /// Get the greedy per-class NMS with the IoU threshold of `darknet detector test`.
+    fn default() -> Self {
+        Self {
+            suppression: Suppression::Greedy,
+            iou_threshold: 0.45,
+            class_agnostic: false,
+            score_threshold: 0.0,
+        }
+    }
+}
+
+/// Suppress overlapping detections in place.
+///
+/// Like libdarknet, the detections are kept, and the suppressed class probabilities are set to
+/// zero or decayed. Detections are then sorted by their best class probability in descending
+/// order. Use [OwnedDetection::best_class] with a threshold to filter them.
+pub fn nms(detections: &mut [OwnedDetection], options: &NmsOptions) {
+    if options.class_agnostic {
+        suppress(detections, None, options);
+    } else {
+        let num_classes = detections
+            .iter()
+            .map(OwnedDetection::num_classes)
+            .max()
+            .unwrap_or(0);
+        for class in 0..num_classes {
+            suppress(detections, Some(class), options);
+        }
+    }
+    detections.sort_by(|a, b| compare_scores(best_score(b), best_score(a)));
+}
+
+/// Suppress the scores of a class, or of all classes if \[class\] is [None].
+fn suppress(detections: &mut [OwnedDetection], class: Option<usize>, options: &NmsOptions) {
+    let score = |detection: &OwnedDetection| match class {
+        Some(class) => detection.probabilities.get(class).copied().unwrap_or(0.0),
+        None => best_score(detection),
+    };
+    let scale = |detection: &mut OwnedDetection, factor: f32| match class {
+        Some(class) => detection.probabilities[class] *= factor,
+        None => detection
+            .probabilities
+            .iter_mut()
+            .for_each(|prob| *prob *= factor),
+    };
+
+    let mut remaining: Vec<usize> = (0..detections.len())
+        .filter(|&index| score(&detections[index]) > 0.0)
+        .collect();
+    while !remaining.is_empty() {
+        // take the first box with the highest score, so ties keep the input order
+        let position = (0..remaining.len())
+            .rev()
+            .max_by(|&a, &b| {
+                compare_scores(
+                    score(&detections[remaining[a]]),
+                    score(&detections[remaining[b]]),
+                )
+            })
+            .unwrap();
+        let kept = detections[remaining.remove(position)].bounding_box();
+
+        for &index in &remaining {
+            let detection = &mut detections[index];
+            let factor =
+                options
+                    .suppression
+                    .decay(&kept, &detection.bounding_box(), options.iou_threshold);
+            if factor < 1.0 {
+                scale(detection, factor);
+            }
+            if score(detection) < options.score_threshold {
+                scale(detection, 0.0);
+            }
+        }
+        remaining.retain(|&index| score(&detections[index]) > 0.0);
+    }
+}
+
+/// Fuse the detections of one or more models with weighted box fusion (WBF).
+///
+/// Boxes of the same class are clustered in descending order of score, joining the cluster
+/// whose fused box overlaps them with an IoU above \[iou_threshold\]. A fused box is the
+/// score-weighted average of the corners of its boxes, and its score is the average score,
+/// reduced when fewer models than given contributed to it. The scores of each model are
+/// multiplied by its weight in \[weights\], which defaults to 1. Scores below
+/// \[score_threshold\] are skipped.
+///
+/// Each fused detection has the fused score as the probability of its class, and zero for
+/// the others. They are returned in descending order of score.
+pub fn weighted_box_fusion<D>(
+    models: &[D],
+    weights: Option<&[f32]>,
+    iou_threshold: f32,
+    score_threshold: f32,
+) -> Vec<OwnedDetection>
+where
+    D: AsRef<[OwnedDetection]>,
+{
+    let weight = |model: usize| weights.and_then(|weights| weights.get(model).copied());
+    let total_weight: f32 = (0..models.len())
+        .map(|model| weight(model).unwrap_or(1.0))
+        .sum();
+    let num_classes = models
+        .iter()
+        .flat_map(|detections| detections.as_ref())
+        .map(OwnedDetection::num_classes)
+        .max()
+        .unwrap_or(0);
+
+    // (class, weighted score, detection) of each box above the threshold
+    let mut entries: Vec<(usize, f32, &OwnedDetection)> = vec![];
+    for (model, detections) in models.iter().enumerate() {
+        let weight = weight(model).unwrap_or(1.0);
+        for detection in detections.as_ref() {
+            for (class, &prob) in detection.probabilities.iter().enumerate() {
+                if prob >= score_threshold && prob > 0.0 {
+                    entries.push((class, prob * weight, detection));
+                }
+            }
+        }
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0).then(compare_scores(b.1, a.1)));
+
+    let mut clusters: Vec<Cluster> = vec![];
+    let mut fused = vec![];
+    for class in 0..num_classes {
+        clusters.clear();
+        for &(_, score, detection) in entries.iter().filter(|entry| entry.0 == class) {
+            let bbox = detection.bounding_box();
+            let matched = clusters
+                .iter_mut()
+                .map(|cluster| {
+                    let iou = cluster.bbox().iou(&bbox);
+                    (cluster, iou)
+                })
+                .filter(|(_, iou)| *iou > iou_threshold)
+                .max_by(|a, b| compare_scores(a.1, b.1))
+                .map(|(cluster, _)| cluster);
+            match matched {
+                Some(cluster) => cluster.push(&bbox, score, detection.objectness),
+                None => {
+                    let mut cluster = Cluster::default();
+                    cluster.push(&bbox, score, detection.objectness);
+                    clusters.push(cluster);
+                }
+            }
+        }
+
+        fused.extend(clusters.iter().map(|cluster| {
+            let count = cluster.count as f32;
+            let score = cluster.score_sum / count * count.min(models.len() as f32) / total_weight;
+            let mut probabilities = vec![0.0; num_classes];
+            probabilities[class] = score;
+            OwnedDetection {
+                bbox: BBox::from(cluster.bbox()),
+                objectness: cluster.objectness_sum / count,
+                probabilities,
+                uc: None,
+                sort_class: class,
+            }
+        }));
+    }
+
+    fused.sort_by(|a, b| compare_scores(best_score(b), best_score(a)));
+    fused
+}
+
+/// A cluster of boxes in [weighted_box_fusion].
+#[derive(Debug, Default)]
+struct Cluster {
+    /// The score-weighted sums of the corners.
+    corners: [f32; 4],
+    score_sum: f32,
+    objectness_sum: f32,
+    count: usize,
+}
+
+impl Cluster {
+    fn push(&mut self, bbox: &BoundingBox, score: f32, objectness: f32) {
+        self.corners
+            .iter_mut()
+            .zip(&bbox.to_xyxy())
+            .for_each(|(sum, corner)| *sum += corner * score);
+        self.score_sum += score;
+        self.objectness_sum += objectness;
+        self.count += 1;
+    }
+
+    fn bbox(&self) -> BoundingBox {
+        let [left, top, right, bottom] = self.corners.map(|sum| sum / self.score_sum);
+        BoundingBox::from_xyxy(left, top, right, bottom)
+    }
+}
+
+fn best_score(detection: &OwnedDetection) -> f32 {
+    detection.best_class(None).map_or(0.0, |(_, prob)| prob)
+}
+
+fn compare_scores(a: f32, b: f32) -> Ordering {
+    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+}
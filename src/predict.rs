@@ -0,0 +1,187 @@
+use crate::{kinds::NmsKind, nms::NmsOptions};
+
+/// The options of [Network::predict_with](crate::Network::predict_with).
+///
+/// The defaults are the same as `darknet detector test`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredictOptions {
+    /// The probability threshold of detected classes. Defaults to 0.25.
+    pub thresh: f32,
+    /// The probability threshold in the class hierarchy of `[region]` layers with a tree.
+    /// Defaults to 0.5.
+    pub hier_thresh: f32,
+    /// The IoU threshold of NMS, or 0 to disable NMS. Defaults to 0.45.
+    pub nms: f32,
+    /// The NMS kind, or [None] to use the `nms_kind` of the output layer. Defaults to [None].
+    pub nms_kind: Option<NmsKind>,
+    /// Whether to letterbox the image to keep its ratio. Defaults to `false`.
+    pub letter_box: bool,
+    /// Whether the boxes are normalized by the image size, or in pixels. Defaults to `true`.
+    pub relative: bool,
+    /// The allowed class indexes, or [None] to allow all classes. Defaults to [None].
+    pub classes: Option<Vec<usize>>,
+    /// The maximum number of detections, or [None] for no limit. Defaults to [None].
+    pub max_detections: Option<usize>,
+}
+
+impl PredictOptions {
+    /// Create the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the probability threshold of detected classes.
+    pub fn thresh(mut self, thresh: f32) -> Self {
+        self.thresh = thresh;
+        self
+    }
+
+    /// Set the probability threshold in the class hierarchy.
+    pub fn hier_thresh(mut self, hier_thresh: f32) -> Self {
+        self.hier_thresh = hier_thresh;
+        self
+    }
+
+    /// Set the IoU threshold of NMS, or 0 to disable NMS.
+    pub fn nms(mut self, nms: f32) -> Self {
+        self.nms = nms;
+        self
+    }
+
+    /// Set the NMS kind instead of the one of the output layer.
+    pub fn nms_kind(mut self, nms_kind: NmsKind) -> Self {
+        self.nms_kind = Some(nms_kind);
+        self
+    }
+
+    /// Set whether to letterbox the image.
+    pub fn letter_box(mut self, letter_box: bool) -> Self {
+        self.letter_box = letter_box;
+        self
+    }
+
+    /// Set whether the boxes are normalized by the image size, or in pixels.
+    pub fn relative(mut self, relative: bool) -> Self {
+        self.relative = relative;
+        self
+    }
+
+    /// Only detect the classes with the given indexes.
+    pub fn classes<I>(mut self, classes: I) -> Self
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        self.classes = Some(classes.into_iter().collect());
+        self
+    }
+
+    /// Set the maximum number of detections.
+    pub fn max_detections(mut self, max_detections: usize) -> Self {
+        self.max_detections = Some(max_detections);
+        self
+    }
+
+    /// Check whether detections are filtered by classes or count after NMS.
+    pub(crate) fn filters(&self) -> bool {
+        self.classes.is_some() || self.max_detections.is_some()
+    }
+}
+
+impl Default for PredictOptions {
+    fn default() -> Self {
+        Self {
+            thresh: 0.25,
+            hier_thresh: 0.5,
+            nms: 0.45,
+            nms_kind: None,
+            letter_box: false,
+            relative: true,
+            classes: None,
+            max_detections: None,
+        }
+    }
+}
+
+/// The options of [Network::predict_tiled](crate::Network::predict_tiled).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileOptions {
+    /// The tile width in pixels, or [None] for the network input width. Defaults to [None].
+    pub tile_width: Option<usize>,
+    /// The tile height in pixels, or [None] for the network input height. Defaults to [None].
+    pub tile_height: Option<usize>,
+    /// The overlap of neighboring tiles as a ratio of the tile size, in \[0, 1). Defaults to
+    /// 0.2.
+    pub overlap: f32,
+    /// Whether to also predict the whole image, which is downscaled to the network input.
+    /// Defaults to `true`.
+    pub full_image: bool,
+    /// The options of the prediction of each tile and of the whole image.
+    pub predict: PredictOptions,
+    /// The NMS across tiles, or [None] to keep all boxes. Defaults to greedy NMS.
+    pub nms: Option<NmsOptions>,
+}
+
+impl TileOptions {
+    /// Create the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the tile size in pixels.
+    pub fn tile_size(mut self, width: usize, height: usize) -> Self {
+        self.tile_width = Some(width);
+        self.tile_height = Some(height);
+        self
+    }
+
+    /// Set the overlap of neighboring tiles as a ratio of the tile size.
+    pub fn overlap(mut self, overlap: f32) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Set whether to also predict the whole image.
+    pub fn full_image(mut self, full_image: bool) -> Self {
+        self.full_image = full_image;
+        self
+    }
+
+    /// Set the options of the prediction of each tile.
+    pub fn predict(mut self, predict: PredictOptions) -> Self {
+        self.predict = predict;
+        self
+    }
+
+    /// Set the NMS across tiles, or [None] to keep all boxes.
+    pub fn nms(mut self, nms: Option<NmsOptions>) -> Self {
+        self.nms = nms;
+        self
+    }
+
+    /// Get the left or top offsets of the tiles along a side of \[size\] pixels.
+    ///
+    /// The tiles are \[overlap\] apart, and the last one is moved back to end at the edge.
+    pub(crate) fn offsets(&self, size: usize, tile: usize) -> Vec<usize> {
+        if tile >= size {
+            return vec![0];
+        }
+        let overlap = self.overlap.clamp(0.0, 0.99);
+        let step = ((tile as f32 * (1.0 - overlap)).round() as usize).max(1);
+        let mut offsets: Vec<_> = (0..size - tile).step_by(step).collect();
+        offsets.push(size - tile);
+        offsets
+    }
+}
+
+impl Default for TileOptions {
+    fn default() -> Self {
+        Self {
+            tile_width: None,
+            tile_height: None,
+            overlap: 0.2,
+            full_image: true,
+            predict: PredictOptions::default(),
+            nms: Some(NmsOptions::default()),
+        }
+    }
+}
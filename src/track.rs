@@ -0,0 +1,757 @@
+//! Multi-object tracking over the detections of video frames.
+//!
+//! Call [SortTracker::update] or [ByteTracker::update] with the detections of each frame, in
+//! order, to get the [Track]s with stable IDs. Both trackers predict the boxes with the
+//! constant velocity Kalman filter of SORT, and match them to detections by IoU with an
+//! optimal assignment, so the results only depend on the detections.
+
+use crate::{bbox::BoundingBox, detections::OwnedDetection};
+
+/// The state of a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrackState {
+    /// The track is new, and not matched in enough frames to be reported.
+    Tentative,
+    /// The track is matched in the current frame.
+    Confirmed,
+    /// The track is not matched in the current frame, and is kept to be matched again.
+    Lost,
+}
+
+/// A tracked object.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Track {
+    /// The track ID, unique in a tracker and starting from 1.
+    pub id: u64,
+    /// The box estimated by the Kalman filter, in the coordinates of the detections.
+    pub bbox: BoundingBox,
+    /// The velocity of the box center per frame.
+    pub velocity: [f32; 2],
+    /// The class of the last matched detection.
+    pub class: usize,
+    /// The score of the last matched detection.
+    pub score: f32,
+    /// The number of frames since the track is created.
+    pub age: usize,
+    /// The number of frames where the track is matched, after the one creating it.
+    pub hits: usize,
+    /// The number of frames since the track is last matched.
+    pub time_since_update: usize,
+    /// The state of the track.
+    pub state: TrackState,
+}
+
+/// The options of [SortTracker].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortOptions {
+    /// The number of frames a track is kept without matches.
+    pub max_age: usize,
+    /// The number of consecutive matches for a track to be confirmed.
+    pub min_hits: usize,
+    /// The minimum IoU between a detection and a predicted box to match them.
+    pub iou_threshold: f32,
+    /// Only match detections and tracks of the same class.
+    pub class_aware: bool,
+}
+
+impl Default for SortOptions {
+    /// Get the defaults of the SORT paper.
+    fn default() -> Self {
+        Self {
+            max_age: 1,
+            min_hits: 3,
+            iou_threshold: 0.3,
+            class_aware: false,
+        }
+    }
+}
+
+/// The SORT tracker, which matches all detections to the predicted boxes at once.
+#[derive(Debug, Clone)]
+pub struct SortTracker {
+    options: SortOptions,
+    tracklets: Vec<Tracklet>,
+    frame_count: usize,
+    next_id: u64,
+}
+
+impl SortTracker {
+    /// Create a tracker without tracks.
+    pub fn new(options: SortOptions) -> Self {
+        Self {
+            options,
+            tracklets: vec![],
+            frame_count: 0,
+            next_id: 1,
+        }
+    }
+
+    /// Get the options.
+    pub fn options(&self) -> &SortOptions {
+        &self.options
+    }
+
+    /// Get the number of updated frames.
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Update the tracks with the detections of the next frame, and return all tracks that are
+    /// kept, ordered by ID.
+    ///
+    /// A detection is matched by its most probable class. Detections without a positive class
+    /// probability are ignored.
+    pub fn update<I>(&mut self, detections: I) -> Vec<Track>
+    where
+        I: IntoIterator,
+        I::Item: Into<OwnedDetection>,
+    {
+        let SortOptions {
+            max_age,
+            min_hits,
+            iou_threshold,
+            class_aware,
+        } = self.options;
+        self.frame_count += 1;
+        let observations = observations(detections);
+
+        self.tracklets.iter_mut().for_each(Tracklet::predict);
+        self.tracklets.retain(|tracklet| tracklet.bbox().is_some());
+
+        let tracklets: Vec<_> = self.tracklets.iter().collect();
+        let obs: Vec<_> = observations.iter().collect();
+        let costs = iou_costs(&tracklets, &obs, class_aware, false);
+        let matches = assign(&costs, tracklets.len(), obs.len(), 1.0 - iou_threshold);
+
+        let mut matched = vec![false; observations.len()];
+        for (tracklet, observation) in matches {
+            self.tracklets[tracklet].update(&observations[observation]);
+            matched[observation] = true;
+        }
+        for (observation, _) in observations
+            .iter()
+            .zip(&matched)
+            .filter(|(_, &matched)| !matched)
+        {
+            self.tracklets
+                .push(Tracklet::new(self.next_id, observation));
+            self.next_id += 1;
+        }
+        self.tracklets
+            .retain(|tracklet| tracklet.time_since_update <= max_age);
+
+        let frame_count = self.frame_count;
+        self.tracklets
+            .iter()
+            .map(|tracklet| {
+                let state = if tracklet.time_since_update > 0 {
+                    TrackState::Lost
+                } else if tracklet.hit_streak >= min_hits || frame_count <= min_hits {
+                    TrackState::Confirmed
+                } else {
+                    TrackState::Tentative
+                };
+                tracklet.track(state)
+            })
+            .collect()
+    }
+}
+
+impl Default for SortTracker {
+    fn default() -> Self {
+        Self::new(SortOptions::default())
+    }
+}
+
+/// The options of [ByteTracker].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByteOptions {
+    /// The minimum score of high score detections, which are matched first. A new track needs
+    /// a score of 0.1 above it.
+    pub track_thresh: f32,
+    /// The minimum score of low score detections, which only continue tracks.
+    pub low_thresh: f32,
+    /// The maximum cost of matching high score detections, where the cost is 1 - IoU.
+    pub match_thresh: f32,
+    /// The number of frames a lost track is kept.
+    pub track_buffer: usize,
+    /// Multiply the IoUs of high score detections by their scores before matching.
+    pub fuse_score: bool,
+    /// Only match detections and tracks of the same class.
+    pub class_aware: bool,
+}
+
+impl Default for ByteOptions {
+    /// Get the defaults of ByteTrack.
+    fn default() -> Self {
+        Self {
+            track_thresh: 0.5,
+            low_thresh: 0.1,
+            match_thresh: 0.8,
+            track_buffer: 30,
+            fuse_score: true,
+            class_aware: false,
+        }
+    }
+}
+
+/// The ByteTrack tracker, which matches high score detections first, then continues the
+/// unmatched tracks with low score detections.
+#[derive(Debug, Clone)]
+pub struct ByteTracker {
+    options: ByteOptions,
+    tracklets: Vec<Tracklet>,
+    frame_count: usize,
+    next_id: u64,
+}
+
+impl ByteTracker {
+    /// The maximum cost of matching low score detections.
+    const LOW_MATCH_THRESH: f32 = 0.5;
+    /// The maximum cost of matching tentative tracks.
+    const TENTATIVE_MATCH_THRESH: f32 = 0.7;
+    /// The maximum cost between a tracked and a lost track to be duplicates.
+    const DUPLICATE_THRESH: f32 = 0.15;
+
+    /// Create a tracker without tracks.
+    pub fn new(options: ByteOptions) -> Self {
+        Self {
+            options,
+            tracklets: vec![],
+            frame_count: 0,
+            next_id: 1,
+        }
+    }
+
+    /// Get the options.
+    pub fn options(&self) -> &ByteOptions {
+        &self.options
+    }
+
+    /// Get the number of updated frames.
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Update the tracks with the detections of the next frame, and return all tracks that are
+    /// kept, ordered by ID.
+    ///
+    /// A detection is matched by its most probable class. Detections without a positive class
+    /// probability are ignored.
+    pub fn update<I>(&mut self, detections: I) -> Vec<Track>
+    where
+        I: IntoIterator,
+        I::Item: Into<OwnedDetection>,
+    {
+        let ByteOptions {
+            track_thresh,
+            low_thresh,
+            match_thresh,
+            track_buffer,
+            fuse_score,
+            class_aware,
+        } = self.options;
+        self.frame_count += 1;
+        let observations = observations(detections);
+        let (high, low): (Vec<_>, Vec<_>) = observations
+            .iter()
+            .filter(|observation| observation.score > low_thresh)
+            .partition(|observation| observation.score > track_thresh);
+
+        self.tracklets.iter_mut().for_each(Tracklet::predict);
+        // the tentative tracks are only matched with the high score detections left
+        let (pool, tentative): (Vec<usize>, Vec<usize>) =
+            (0..self.tracklets.len()).partition(|&index| self.tracklets[index].activated);
+        let mut matched = vec![false; self.tracklets.len()];
+
+        // match the high score detections
+        let unmatched_high = self.match_tracklets(
+            &pool,
+            &high,
+            &mut matched,
+            match_thresh,
+            class_aware,
+            fuse_score,
+        );
+
+        // continue the unmatched tracks that were not lost with the low score detections
+        let remaining: Vec<_> = pool
+            .iter()
+            .copied()
+            .filter(|&index| !matched[index] && self.tracklets[index].time_since_update <= 1)
+            .collect();
+        self.match_tracklets(
+            &remaining,
+            &low,
+            &mut matched,
+            Self::LOW_MATCH_THRESH,
+            class_aware,
+            false,
+        );
+
+        // confirm the tentative tracks with the remaining high score detections
+        let unmatched_high = self.match_tracklets(
+            &tentative,
+            &unmatched_high,
+            &mut matched,
+            Self::TENTATIVE_MATCH_THRESH,
+            class_aware,
+            fuse_score,
+        );
+
+        let frame_count = self.frame_count;
+        let mut kept = matched.clone();
+        for (index, tracklet) in self.tracklets.iter().enumerate() {
+            if !matched[index] && tracklet.activated {
+                // lost tracks are kept for the track buffer
+                kept[index] = tracklet.time_since_update <= track_buffer;
+            }
+        }
+        let mut index = 0;
+        self.tracklets.retain(|_| {
+            index += 1;
+            kept[index - 1]
+        });
+
+        for observation in unmatched_high {
+            if observation.score < track_thresh + 0.1 {
+                continue;
+            }
+            let mut tracklet = Tracklet::new(self.next_id, observation);
+            // the tracks of the first frame are confirmed at once
+            tracklet.activated = frame_count == 1;
+            self.tracklets.push(tracklet);
+            self.next_id += 1;
+        }
+        self.remove_duplicates();
+
+        self.tracklets
+            .iter()
+            .map(|tracklet| {
+                let state = if tracklet.time_since_update > 0 {
+                    TrackState::Lost
+                } else if tracklet.activated {
+                    TrackState::Confirmed
+                } else {
+                    TrackState::Tentative
+                };
+                tracklet.track(state)
+            })
+            .collect()
+    }
+
+    /// Match tracks with detections, update the matched tracks, and return the unmatched
+    /// detections.
+    fn match_tracklets<'a>(
+        &mut self,
+        indexes: &[usize],
+        observations: &[&'a Observation],
+        matched: &mut [bool],
+        max_cost: f32,
+        class_aware: bool,
+        fuse_score: bool,
+    ) -> Vec<&'a Observation> {
+        let tracklets: Vec<_> = indexes
+            .iter()
+            .map(|&index| &self.tracklets[index])
+            .collect();
+        let costs = iou_costs(&tracklets, observations, class_aware, fuse_score);
+        let matches = assign(&costs, tracklets.len(), observations.len(), max_cost);
+
+        let mut unmatched = vec![true; observations.len()];
+        for (tracklet, observation) in matches {
+            let index = indexes[tracklet];
+            let tracklet = &mut self.tracklets[index];
+            tracklet.update(observations[observation]);
+            tracklet.activated = true;
+            matched[index] = true;
+            unmatched[observation] = false;
+        }
+        observations
+            .iter()
+            .zip(unmatched)
+            .filter(|(_, unmatched)| *unmatched)
+            .map(|(&observation, _)| observation)
+            .collect()
+    }
+
+    /// Remove the tracked and lost tracks that overlap, keeping the older one.
+    fn remove_duplicates(&mut self) {
+        let mut removed = vec![false; self.tracklets.len()];
+        for tracked in 0..self.tracklets.len() {
+            for lost in 0..self.tracklets.len() {
+                let (a, b) = (&self.tracklets[tracked], &self.tracklets[lost]);
+                if !a.activated || a.time_since_update > 0 || b.time_since_update == 0 {
+                    continue;
+                }
+                let overlap = match (a.bbox(), b.bbox()) {
+                    (Some(a), Some(b)) => a.iou(&b),
+                    _ => 0.0,
+                };
+                if 1.0 - overlap < Self::DUPLICATE_THRESH {
+                    if a.age > b.age {
+                        removed[lost] = true;
+                    } else {
+                        removed[tracked] = true;
+                    }
+                }
+            }
+        }
+        let mut index = 0;
+        self.tracklets.retain(|_| {
+            index += 1;
+            !removed[index - 1]
+        });
+    }
+}
+
+impl Default for ByteTracker {
+    fn default() -> Self {
+        Self::new(ByteOptions::default())
+    }
+}
+
+/// The box, class and score of a detection.
+#[derive(Debug, Clone, Copy)]
+struct Observation {
+    bbox: BoundingBox,
+    class: usize,
+    score: f32,
+}
+
+fn observations<I>(detections: I) -> Vec<Observation>
+where
+    I: IntoIterator,
+    I::Item: Into<OwnedDetection>,
+{
+    detections
+        .into_iter()
+        .filter_map(|detection| {
+            let detection = detection.into();
+            let (class, score) = detection.best_class(Some(0.0))?;
+            Some(Observation {
+                bbox: detection.bounding_box(),
+                class,
+                score,
+            })
+        })
+        .collect()
+}
+
+/// A track with its Kalman filter.
+#[derive(Debug, Clone)]
+struct Tracklet {
+    id: u64,
+    filter: KalmanFilter,
+    class: usize,
+    score: f32,
+    age: usize,
+    hits: usize,
+    hit_streak: usize,
+    time_since_update: usize,
+    /// Whether the track was matched after its first frame, used by [ByteTracker].
+    activated: bool,
+}
+
+impl Tracklet {
+    fn new(id: u64, observation: &Observation) -> Self {
+        Self {
+            id,
+            filter: KalmanFilter::new(&observation.bbox),
+            class: observation.class,
+            score: observation.score,
+            age: 0,
+            hits: 0,
+            hit_streak: 0,
+            time_since_update: 0,
+            activated: false,
+        }
+    }
+
+    fn predict(&mut self) {
+        self.filter.predict();
+        self.age += 1;
+        if self.time_since_update > 0 {
+            self.hit_streak = 0;
+        }
+        self.time_since_update += 1;
+    }
+
+    fn update(&mut self, observation: &Observation) {
+        self.filter.update(&observation.bbox);
+        self.class = observation.class;
+        self.score = observation.score;
+        self.hits += 1;
+        self.hit_streak += 1;
+        self.time_since_update = 0;
+    }
+
+    fn bbox(&self) -> Option<BoundingBox> {
+        self.filter.bbox()
+    }
+
+    fn track(&self, state: TrackState) -> Track {
+        let [vx, vy] = self.filter.velocity();
+        Track {
+            id: self.id,
+            bbox: self.bbox().unwrap_or_default(),
+            velocity: [vx as f32, vy as f32],
+            class: self.class,
+            score: self.score,
+            age: self.age,
+            hits: self.hits,
+            time_since_update: self.time_since_update,
+            state,
+        }
+    }
+}
+
+/// The Kalman filter of SORT.
+///
+/// The state is the box center, area and aspect ratio, with the velocities of the center and
+/// the area. The covariances only couple each value with its velocity, so the filter is kept
+/// as independent filters of (value, velocity) pairs.
+#[derive(Debug, Clone)]
+struct KalmanFilter {
+    center_x: KalmanPair,
+    center_y: KalmanPair,
+    area: KalmanPair,
+    /// The aspect ratio, which is constant, and its variance.
+    ratio: (f64, f64),
+}
+
+impl KalmanFilter {
+    /// The measurement noise of the ratio.
+    const RATIO_NOISE: f64 = 10.0;
+
+    fn new(bbox: &BoundingBox) -> Self {
+        let [x, y, area, ratio] = measurement(bbox);
+        Self {
+            center_x: KalmanPair::new(x, 1.0, 0.01),
+            center_y: KalmanPair::new(y, 1.0, 0.01),
+            area: KalmanPair::new(area, 10.0, 0.0001),
+            ratio: (ratio, 10.0),
+        }
+    }
+
+    fn predict(&mut self) {
+        // an area shrinking below zero stops shrinking
+        if self.area.state[0] + self.area.state[1] <= 0.0 {
+            self.area.state[1] = 0.0;
+        }
+        self.center_x.predict();
+        self.center_y.predict();
+        self.area.predict();
+        self.ratio.1 += 1.0;
+    }
+
+    fn update(&mut self, bbox: &BoundingBox) {
+        let [x, y, area, ratio] = measurement(bbox);
+        self.center_x.update(x);
+        self.center_y.update(y);
+        self.area.update(area);
+        let (value, variance) = &mut self.ratio;
+        let gain = *variance / (*variance + Self::RATIO_NOISE);
+        *value += gain * (ratio - *value);
+        *variance *= 1.0 - gain;
+    }
+
+    /// Get the estimated box, or [None] if the area or ratio is not positive.
+    fn bbox(&self) -> Option<BoundingBox> {
+        let (area, ratio) = (self.area.state[0], self.ratio.0);
+        if !(area > 0.0 && ratio > 0.0) {
+            return None;
+        }
+        let w = (area * ratio).sqrt();
+        let h = area / w;
+        Some(BoundingBox::new(
+            self.center_x.state[0] as f32,
+            self.center_y.state[0] as f32,
+            w as f32,
+            h as f32,
+        ))
+    }
+
+    fn velocity(&self) -> [f64; 2] {
+        [self.center_x.state[1], self.center_y.state[1]]
+    }
+}
+
+/// The Kalman filter of a value and its velocity, where the value is measured.
+#[derive(Debug, Clone)]
+struct KalmanPair {
+    /// The value and the velocity.
+    state: [f64; 2],
+    covariance: [[f64; 2]; 2],
+    measurement_noise: f64,
+    velocity_noise: f64,
+}
+
+impl KalmanPair {
+    fn new(value: f64, measurement_noise: f64, velocity_noise: f64) -> Self {
+        Self {
+            state: [value, 0.0],
+            // the velocity is not observed, so it is uncertain
+            covariance: [[10.0, 0.0], [0.0, 10_000.0]],
+            measurement_noise,
+            velocity_noise,
+        }
+    }
+
+    fn predict(&mut self) {
+        let [[p00, p01], [_, p11]] = self.covariance;
+        self.state[0] += self.state[1];
+        let p00 = p00 + 2.0 * p01 + p11 + 1.0;
+        let p01 = p01 + p11;
+        let p11 = p11 + self.velocity_noise;
+        self.covariance = [[p00, p01], [p01, p11]];
+    }
+
+    fn update(&mut self, value: f64) {
+        let [[p00, p01], [_, p11]] = self.covariance;
+        let innovation = value - self.state[0];
+        let gain = [
+            p00 / (p00 + self.measurement_noise),
+            p01 / (p00 + self.measurement_noise),
+        ];
+        self.state[0] += gain[0] * innovation;
+        self.state[1] += gain[1] * innovation;
+        let q00 = (1.0 - gain[0]) * p00;
+        let q01 = (1.0 - gain[0]) * p01;
+        let q11 = p11 - gain[1] * p01;
+        self.covariance = [[q00, q01], [q01, q11]];
+    }
+}
+
+/// Get the center, area and aspect ratio of a box.
+fn measurement(bbox: &BoundingBox) -> [f64; 4] {
+    let (w, h) = (bbox.w as f64, bbox.h as f64);
+    [bbox.x as f64, bbox.y as f64, w * h, w / h]
+}
+
+/// Get the costs of matching tracks with detections, which are 1 - IoU.
+fn iou_costs(
+    tracklets: &[&Tracklet],
+    observations: &[&Observation],
+    class_aware: bool,
+    fuse_score: bool,
+) -> Vec<Vec<f32>> {
+    tracklets
+        .iter()
+        .map(|tracklet| {
+            let bbox = tracklet.bbox();
+            observations
+                .iter()
+                .map(|observation| match bbox {
+                    Some(_) if class_aware && tracklet.class != observation.class => 1.0,
+                    Some(bbox) => {
+                        let iou = bbox.iou(&observation.bbox);
+                        let similarity = if fuse_score {
+                            iou * observation.score
+                        } else {
+                            iou
+                        };
+                        1.0 - similarity
+                    }
+                    None => 1.0,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Find the matches between rows and columns with the minimum total cost, where a pair costs
+/// at most \[max_cost\], and leaving a row or a column unmatched costs half of it.
+fn assign(costs: &[Vec<f32>], rows: usize, cols: usize, max_cost: f32) -> Vec<(usize, usize)> {
+    const FORBIDDEN: f64 = 1e6;
+    if rows == 0 || cols == 0 {
+        return vec![];
+    }
+
+    // each row and column has a dummy partner to stay unmatched with
+    let size = rows + cols;
+    let unmatched = max_cost as f64 / 2.0;
+    let mut matrix = vec![vec![FORBIDDEN; size]; size];
+    for (row, costs) in costs.iter().enumerate() {
+        for (col, &cost) in costs.iter().enumerate() {
+            if cost <= max_cost {
+                matrix[row][col] = cost as f64;
+            }
+        }
+        matrix[row][cols + row] = unmatched;
+    }
+    for col in 0..cols {
+        matrix[rows + col][col] = unmatched;
+        matrix[rows + col][cols..].fill(0.0);
+    }
+
+    hungarian(&matrix)
+        .into_iter()
+        .enumerate()
+        .filter(|&(row, col)| row < rows && col < cols && matrix[row][col] < FORBIDDEN)
+        .collect()
+}
+
+/// Solve the assignment problem of a square cost matrix, and return the column of each row.
+fn hungarian(costs: &[Vec<f64>]) -> Vec<usize> {
+    let size = costs.len();
+    // the potentials and matches are 1-based, where 0 is a virtual column
+    let mut row_potentials = vec![0.0; size + 1];
+    let mut col_potentials = vec![0.0; size + 1];
+    let mut col_rows = vec![0; size + 1];
+    let mut way = vec![0; size + 1];
+
+    for row in 1..=size {
+        col_rows[0] = row;
+        let mut col = 0;
+        let mut min_values = vec![f64::INFINITY; size + 1];
+        let mut used = vec![false; size + 1];
+        loop {
+            used[col] = true;
+            let current_row = col_rows[col];
+            let mut delta = f64::INFINITY;
+            let mut next_col = 0;
+            for other in 1..=size {
+                if used[other] {
+                    continue;
+                }
+                let value = costs[current_row - 1][other - 1]
+                    - row_potentials[current_row]
+                    - col_potentials[other];
+                if value < min_values[other] {
+                    min_values[other] = value;
+                    way[other] = col;
+                }
+                if min_values[other] < delta {
+                    delta = min_values[other];
+                    next_col = other;
+                }
+            }
+            for other in 0..=size {
+                if used[other] {
+                    row_potentials[col_rows[other]] += delta;
+                    col_potentials[other] -= delta;
+                } else {
+                    min_values[other] -= delta;
+                }
+            }
+            col = next_col;
+            if col_rows[col] == 0 {
+                break;
+            }
+        }
+        while col != 0 {
+            let previous = way[col];
+            col_rows[col] = col_rows[previous];
+            col = previous;
+        }
+    }
+
+    let mut row_cols = vec![0; size];
+    for col in 1..=size {
+        row_cols[col_rows[col] - 1] = col - 1;
+    }
+    row_cols
+}
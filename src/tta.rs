@@ -0,0 +1,228 @@
+//! Test-time augmentation (TTA) of detections.
+//!
+//! [Network::predict_tta](crate::Network::predict_tta) predicts the [Augmentation]s of an
+//! image, maps their boxes back to the image with [Augmentation::to_image], and merges them
+//! with [TtaMerge]. The merge can also be run alone on the detections of each augmentation.
+
+use crate::{
+    bbox::BoundingBox,
+    detections::OwnedDetection,
+    image::Image,
+    nms::{self, NmsOptions},
+    predict::PredictOptions,
+};
+
+/// An augmentation of an image, which is flipped, then scaled at the center of the image.
+///
+/// A scale below 1 shrinks the image and pads it with gray like a letterbox, and a scale
+/// above 1 enlarges the image and crops its center.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Augmentation {
+    /// Whether to flip the image horizontally.
+    pub horizontal_flip: bool,
+    /// The scale of the image content.
+    pub scale: f32,
+}
+
+impl Augmentation {
+    /// The image itself.
+    pub const IDENTITY: Self = Self {
+        horizontal_flip: false,
+        scale: 1.0,
+    };
+
+    /// Create an augmentation.
+    pub fn new(horizontal_flip: bool, scale: f32) -> Self {
+        Self {
+            horizontal_flip,
+            scale,
+        }
+    }
+
+    /// Create a horizontal flip.
+    pub fn horizontal_flip() -> Self {
+        Self::new(true, 1.0)
+    }
+
+    /// Create a scale of the image content.
+    pub fn scale(scale: f32) -> Self {
+        Self::new(false, scale)
+    }
+
+    /// Get the augmented image, which has the size of the original one.
+    pub fn apply(&self, image: &Image) -> Image {
+        let (width, height, channels) = (image.width(), image.height(), image.channels());
+        let (scaled_width, scaled_height) = self.scaled_size(width, height);
+        let resized;
+        let source = if (scaled_width, scaled_height) == (width, height) {
+            image
+        } else {
+            resized = image.resize(scaled_width, scaled_height);
+            &resized
+        };
+        let (dx, dy) = self.offsets(width, height);
+
+        let mut output = Image::zeros(width, height, channels);
+        let data = output.get_data_mut();
+        let source_data = source.get_data();
+        for channel in 0..channels {
+            for y in 0..height {
+                let source_y = y as isize + dy;
+                for x in 0..width {
+                    let source_x = x as isize + dx;
+                    let inside = (0..scaled_width as isize).contains(&source_x)
+                        && (0..scaled_height as isize).contains(&source_y);
+                    data[(channel * height + y) * width + x] = if inside {
+                        let source_x = if self.horizontal_flip {
+                            scaled_width - 1 - source_x as usize
+                        } else {
+                            source_x as usize
+                        };
+                        source_data[(channel * scaled_height + source_y as usize) * scaled_width
+                            + source_x]
+                    } else {
+                        0.5
+                    };
+                }
+            }
+        }
+        output
+    }
+
+    /// Map a box normalized by the augmented image back to the original image of the size.
+    pub fn to_image(&self, bbox: &BoundingBox, width: usize, height: usize) -> BoundingBox {
+        let (scaled_width, scaled_height) = self.scaled_size(width, height);
+        let (dx, dy) = self.offsets(width, height);
+        let (scale_x, scale_y) = (
+            width as f32 / scaled_width as f32,
+            height as f32 / scaled_height as f32,
+        );
+        let x = (bbox.x * width as f32 + dx as f32) / scaled_width as f32;
+        let y = (bbox.y * height as f32 + dy as f32) / scaled_height as f32;
+        let x = if self.horizontal_flip { 1.0 - x } else { x };
+        BoundingBox::new(x, y, bbox.w * scale_x, bbox.h * scale_y)
+    }
+
+    fn scaled_size(&self, width: usize, height: usize) -> (usize, usize) {
+        let scale = |size: usize| ((size as f32 * self.scale).round() as usize).max(1);
+        (scale(width), scale(height))
+    }
+
+    /// Get the position of the augmented image in the scaled one.
+    fn offsets(&self, width: usize, height: usize) -> (isize, isize) {
+        let (scaled_width, scaled_height) = self.scaled_size(width, height);
+        (
+            (scaled_width as isize - width as isize) / 2,
+            (scaled_height as isize - height as isize) / 2,
+        )
+    }
+}
+
+impl Default for Augmentation {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// The way to merge the detections of the augmentations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TtaMerge {
+    /// Suppress the overlapping detections of all augmentations with [nms::nms].
+    Nms(NmsOptions),
+    /// Fuse the detections with [nms::weighted_box_fusion], where each augmentation is a model.
+    WeightedBoxFusion {
+        /// The minimum IoU of a box with a fused box to join it.
+        iou_threshold: f32,
+        /// The minimum score of the fused boxes.
+        score_threshold: f32,
+    },
+}
+
+impl TtaMerge {
+    /// Merge the detections of each augmentation, which are in the coordinates of the image.
+    ///
+    /// The detections without a class probability left are removed, and the rest are sorted by
+    /// their best class probability in descending order.
+    pub fn merge<D>(&self, augmentations: &[D]) -> Vec<OwnedDetection>
+    where
+        D: AsRef<[OwnedDetection]>,
+    {
+        let mut detections = match *self {
+            Self::Nms(options) => {
+                let mut detections: Vec<_> = augmentations
+                    .iter()
+                    .flat_map(|detections| detections.as_ref().iter().cloned())
+                    .collect();
+                nms::nms(&mut detections, &options);
+                detections
+            }
+            Self::WeightedBoxFusion {
+                iou_threshold,
+                score_threshold,
+            } => nms::weighted_box_fusion(augmentations, None, iou_threshold, score_threshold),
+        };
+        detections.retain(|detection| detection.best_class(Some(0.0)).is_some());
+        detections
+    }
+}
+
+impl Default for TtaMerge {
+    /// Get the greedy per-class NMS of [NmsOptions::default].
+    fn default() -> Self {
+        Self::Nms(NmsOptions::default())
+    }
+}
+
+/// The options of [Network::predict_tta](crate::Network::predict_tta).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TtaOptions {
+    /// The augmentations to predict. Defaults to the image, the flipped image scaled by 0.83,
+    /// and the image scaled by 0.67, like YOLOv5.
+    pub augmentations: Vec<Augmentation>,
+    /// The way to merge the detections. Defaults to [TtaMerge::default].
+    pub merge: TtaMerge,
+    /// The options of the prediction of each augmentation.
+    pub predict: PredictOptions,
+}
+
+impl TtaOptions {
+    /// Create the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the augmentations to predict.
+    pub fn augmentations<I>(mut self, augmentations: I) -> Self
+    where
+        I: IntoIterator<Item = Augmentation>,
+    {
+        self.augmentations = augmentations.into_iter().collect();
+        self
+    }
+
+    /// Set the way to merge the detections.
+    pub fn merge(mut self, merge: TtaMerge) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    /// Set the options of the prediction of each augmentation.
+    pub fn predict(mut self, predict: PredictOptions) -> Self {
+        self.predict = predict;
+        self
+    }
+}
+
+impl Default for TtaOptions {
+    fn default() -> Self {
+        Self {
+            augmentations: vec![
+                Augmentation::IDENTITY,
+                Augmentation::new(true, 0.83),
+                Augmentation::scale(0.67),
+            ],
+            merge: TtaMerge::default(),
+            predict: PredictOptions::default(),
+        }
+    }
+}
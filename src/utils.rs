@@ -1,6 +1,9 @@
 use crate::Error;
 use std::os::unix::ffi::OsStrExt;
-use std::{ffi::CString, path::Path};
+use std::{
+    ffi::CString,
+    path::{Path, PathBuf},
+};
 
 #[cfg(unix)]
 pub fn path_to_cstring(path: &Path) -> Option<CString> {
@@ -19,3 +22,105 @@ pub fn path_to_cstring(path: &Path) -> Option<CString> {
         .map(|s| CString::new(s.as_bytes()).ok())
         .flatten()
 }
+
+/// In-memory contents exposed by a path, so they can be passed to libdarknet.
+///
+/// On Linux the contents live in an anonymous memory file and never touch the disk. Other
+/// platforms have no such file, so [MemFile::new] fails there, and only [MemFile::new_or_temp]
+/// writes the contents to a private temporary file.
+pub struct MemFile {
+    path: PathBuf,
+    #[cfg(target_os = "linux")]
+    _file: std::fs::File,
+}
+
+impl MemFile {
+    /// Expose the contents without writing them to the filesystem.
+    #[cfg(target_os = "linux")]
+    pub fn new(contents: &[u8]) -> Result<Self, Error> {
+        use std::{
+            fs::File,
+            io::{self, Write},
+            os::unix::io::{AsRawFd, FromRawFd},
+        };
+
+        let fd = unsafe { libc::memfd_create(c"darknet".as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.write_all(contents)?;
+
+        // opening the path gives a new file description starting from the beginning
+        let path = PathBuf::from(format!("/proc/self/fd/{}", file.as_raw_fd()));
+        Ok(Self { path, _file: file })
+    }
+
+    /// Expose the contents without writing them to the filesystem.
+    #[cfg(not(target_os = "linux"))]
+    pub fn new(_contents: &[u8]) -> Result<Self, Error> {
+        Err(Error::UnsupportedError {
+            reason: "in-memory files are only supported on Linux".into(),
+        })
+    }
+
+    /// Expose the contents like [MemFile::new], or through a temporary file that only the
+    /// current user can read on platforms without in-memory files.
+    ///
+    /// The temporary file is removed on drop, but is left behind if the process aborts.
+    #[cfg(target_os = "linux")]
+    pub fn new_or_temp(contents: &[u8]) -> Result<Self, Error> {
+        Self::new(contents)
+    }
+
+    /// Expose the contents like [MemFile::new], or through a temporary file that only the
+    /// current user can read on platforms without in-memory files.
+    ///
+    /// The temporary file is removed on drop, but is left behind if the process aborts.
+    #[cfg(not(target_os = "linux"))]
+    pub fn new_or_temp(contents: &[u8]) -> Result<Self, Error> {
+        use std::{
+            fs::OpenOptions,
+            io::{ErrorKind, Write},
+            sync::atomic::{AtomicUsize, Ordering},
+        };
+
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        loop {
+            let name = format!(
+                "darknet-rust-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::SeqCst)
+            );
+            let path = std::env::temp_dir().join(name);
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            let mut file = match options.open(&path) {
+                Ok(file) => file,
+                // another file took the name, so try the next one
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error.into()),
+            };
+            let memfile = Self { path };
+            file.write_all(contents)?;
+            return Ok(memfile);
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Drop for MemFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,784 @@
+//! Reading, writing and truncating of Darknet `.weights` files without building a network.
+//!
+//! [Weights] reads a file with the layout of a [Config], can be [truncated](Weights::truncate)
+//! to its first layers like `darknet partial`, and is written back with [Weights::save] or
+//! [Weights::write]. [Network::save_weights](crate::Network::save_weights) writes the weights of
+//! a built network in the same format.
+
+use crate::{
+    cfg::{Config, LayerConfig},
+    error::Error,
+    kinds::LayerType,
+};
+use darknet_sys as sys;
+use num_traits::FromPrimitive;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    slice,
+};
+
+/// The version written by [Network::save_weights](crate::Network::save_weights), the same as
+/// upstream darknet.
+const VERSION: [i32; 3] = [0, 2, 5];
+
+/// The header of a weights file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeightsHeader {
+    /// The major version.
+    pub major: i32,
+    /// The minor version.
+    pub minor: i32,
+    /// The revision.
+    pub revision: i32,
+    /// The number of images seen in training.
+    pub seen: u64,
+}
+
+impl WeightsHeader {
+    /// Read the header at the start of a weights file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|error| Error::WeightsOpenError {
+            path: path.to_owned(),
+            error,
+        })?;
+        Self::read(&mut BufReader::new(file))
+    }
+
+    /// Read the header from the start of a weights stream.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let mut version = [0u8; 12];
+        read_full(reader, &mut version, "the header")?;
+        let word = |index: usize| {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(&version[index * 4..index * 4 + 4]);
+            i32::from_le_bytes(bytes)
+        };
+        let (major, minor, revision) = (word(0), word(1), word(2));
+
+        let seen = if seen_is_64bit(major, minor) {
+            let mut bytes = [0u8; 8];
+            read_full(reader, &mut bytes, "the header")?;
+            u64::from_le_bytes(bytes)
+        } else {
+            let mut bytes = [0u8; 4];
+            read_full(reader, &mut bytes, "the header")?;
+            u64::from(u32::from_le_bytes(bytes))
+        };
+
+        Ok(Self {
+            major,
+            minor,
+            revision,
+            seen,
+        })
+    }
+
+    /// Write the header to a weights stream.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let mut bytes = Vec::with_capacity(20);
+        for word in &[self.major, self.minor, self.revision] {
+            bytes.extend(&word.to_le_bytes());
+        }
+        if seen_is_64bit(self.major, self.minor) {
+            bytes.extend(&self.seen.to_le_bytes());
+        } else {
+            bytes.extend(&(self.seen as u32).to_le_bytes());
+        }
+        writer
+            .write_all(&bytes)
+            .map_err(|error| Error::WeightsWriteError { error })
+    }
+
+    /// Check if the connected layer weights are stored transposed.
+    pub fn is_transposed(&self) -> bool {
+        self.major > 1000 || self.minor > 1000
+    }
+}
+
+/// A named block of floats in a weights file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tensor {
+    /// The name of the layer field, such as `biases` or `rolling_mean`.
+    pub name: &'static str,
+    /// The dimensions of the block as stored in the file.
+    pub shape: Vec<usize>,
+    /// The values.
+    pub data: Vec<f32>,
+}
+
+/// The weights of one layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerWeights {
+    /// The index of the layer in the config.
+    pub index: usize,
+    /// The layer type.
+    pub layer_type: LayerType,
+    /// The tensors in file order.
+    pub tensors: Vec<Tensor>,
+}
+
+impl LayerWeights {
+    /// Get the tensor by name.
+    pub fn get(&self, name: &str) -> Option<&Tensor> {
+        self.tensors.iter().find(|tensor| tensor.name == name)
+    }
+}
+
+/// The contents of a weights file, split into layers according to a config.
+///
+/// The file is walked the way libdarknet loads it: convolutional, connected, batchnorm,
+/// weighted shortcut and implicit layers store weights, and layers with `dontload=1` or
+/// `share_index` are skipped. Recurrent and locally connected layers are not supported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weights {
+    /// The header.
+    pub header: WeightsHeader,
+    /// The layers with weights in file order.
+    pub layers: Vec<LayerWeights>,
+    /// The number of leading config layers covered by a partial file such as `yolov4.conv.137`,
+    /// or [None] if the file covers the whole config.
+    pub cutoff: Option<usize>,
+}
+
+impl Weights {
+    /// Read a weights file laid out according to \[config\].
+    pub fn open<P: AsRef<Path>>(path: P, config: &Config) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|error| Error::WeightsOpenError {
+            path: path.to_owned(),
+            error,
+        })?;
+        Self::from_reader(BufReader::new(file), config)
+    }
+
+    /// Read a weights stream laid out according to \[config\].
+    ///
+    /// It returns an [Err] if the stream ends inside a layer or has data after the last layer.
+    pub fn from_reader<R: Read>(mut reader: R, config: &Config) -> Result<Self, Error> {
+        let header = WeightsHeader::read(&mut reader)?;
+        let layout = layout(config, header.is_transposed())?;
+
+        let mut layers = Vec::with_capacity(layout.len());
+        let mut cutoff = None;
+
+        for layer in layout {
+            let mut tensors = Vec::with_capacity(layer.tensors.len());
+            for (name, shape) in layer.tensors {
+                let mut bytes = vec![0u8; shape.iter().product::<usize>() * 4];
+                let count = read_up_to(&mut reader, &mut bytes)?;
+                if count == 0 && tensors.is_empty() {
+                    break;
+                }
+                if count < bytes.len() {
+                    return Err(Error::InvalidWeightsError {
+                        reason: format!("the file ends inside {} of layer {}", name, layer.index),
+                    });
+                }
+                let data = bytes
+                    .chunks_exact(4)
+                    .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .collect();
+                tensors.push(Tensor { name, shape, data });
+            }
+            if tensors.is_empty() {
+                cutoff = Some(layer.index);
+                break;
+            }
+            layers.push(LayerWeights {
+                index: layer.index,
+                layer_type: layer.layer_type,
+                tensors,
+            });
+        }
+
+        let trailing =
+            io::copy(&mut reader, &mut io::sink()).map_err(|error| Error::InvalidWeightsError {
+                reason: format!("failed to read the file: {}", error),
+            })?;
+        if trailing > 0 {
+            return Err(Error::InvalidWeightsError {
+                reason: format!("{} bytes remain after the last layer", trailing),
+            });
+        }
+
+        Ok(Self {
+            header,
+            layers,
+            cutoff,
+        })
+    }
+
+    /// Drop the weights of the layers from index \[cutoff\] on, like `darknet partial` does.
+    pub fn truncate(&mut self, cutoff: usize) {
+        if self.layers.iter().any(|layer| layer.index >= cutoff) {
+            self.layers.retain(|layer| layer.index < cutoff);
+            self.cutoff = Some(self.cutoff.map_or(cutoff, |old| old.min(cutoff)));
+        }
+    }
+
+    /// Save the weights to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|error| Error::WeightsCreateError {
+            path: path.to_owned(),
+            error,
+        })?;
+        self.write(BufWriter::new(file))
+    }
+
+    /// Write the weights to a writer.
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        self.header.write(&mut writer)?;
+        for tensor in self.layers.iter().flat_map(|layer| &layer.tensors) {
+            let bytes: Vec<u8> = tensor
+                .data
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect();
+            writer
+                .write_all(&bytes)
+                .map_err(|error| Error::WeightsWriteError { error })?;
+        }
+        writer
+            .flush()
+            .map_err(|error| Error::WeightsWriteError { error })
+    }
+
+    /// Get the total number of floats.
+    pub fn num_floats(&self) -> usize {
+        self.layers
+            .iter()
+            .flat_map(|layer| &layer.tensors)
+            .map(|tensor| tensor.data.len())
+            .sum()
+    }
+}
+
+/// Write the weights of the first \[cutoff\] layers of a network the way libdarknet saves them.
+pub(crate) fn write_network<W: Write>(
+    net: &sys::network,
+    cutoff: usize,
+    mut writer: W,
+) -> Result<(), Error> {
+    let header = WeightsHeader {
+        major: VERSION[0],
+        minor: VERSION[1],
+        revision: VERSION[2],
+        // save_weights_upto recomputes seen as get_current_iteration * batch * subdivisions
+        seen: unsafe {
+            let images_per_batch = i64::from(net.batch) * i64::from(net.subdivisions);
+            (i64::from(*net.cur_iteration) * images_per_batch).max(0) as u64
+        },
+    };
+    header.write(&mut writer)?;
+
+    let layers = unsafe { slice::from_raw_parts(net.layers, net.n as usize) };
+    for layer in layers.iter().take(cutoff) {
+        unsafe {
+            visit_layer(layer, false, &mut |field| {
+                field.pull();
+                let bytes: Vec<u8> = field
+                    .values()
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect();
+                writer.write_all(&bytes)
+            })
+        }
+        .map_err(|error| Error::WeightsWriteError { error })?;
+    }
+    writer
+        .flush()
+        .map_err(|error| Error::WeightsWriteError { error })
+}
+
+/// Read weights into the first \[cutoff\] layers of a network the way libdarknet loads them,
+/// and return the number of layers visited.
+///
+/// Like libdarknet, loading stops early if the stream ends on a layer boundary.
+pub(crate) fn read_network<R: Read>(
+    net: &mut sys::network,
+    cutoff: usize,
+    mut reader: R,
+) -> Result<usize, Error> {
+    let header = WeightsHeader::read(&mut reader)?;
+    let transposed = header.is_transposed();
+    unsafe {
+        *net.seen = header.seen;
+        let images_per_batch = (i64::from(net.batch) * i64::from(net.subdivisions)).max(1);
+        *net.cur_iteration = (header.seen as i64 / images_per_batch) as i32;
+    }
+
+    let layers = unsafe { slice::from_raw_parts(net.layers, net.n as usize) };
+    for (index, layer) in layers.iter().enumerate().take(cutoff) {
+        if layer.dontload != 0 {
+            continue;
+        }
+        let mut layer_len = 0;
+        let result = unsafe {
+            visit_layer(layer, true, &mut |mut field| {
+                let mut bytes = vec![0u8; field.len * 4];
+                let count = read_up_to(&mut reader, &mut bytes)
+                    .map_err(|error| io::Error::other(error.to_string()))?;
+                layer_len += count;
+                if count < bytes.len() {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                let mut values: Vec<f32> = bytes
+                    .chunks_exact(4)
+                    .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .collect();
+                let matrix = field.flipped.or(field.matrix.filter(|_| transposed));
+                if let Some((rows, cols)) = matrix {
+                    values = (0..rows * cols)
+                        .map(|index| values[(index % cols) * rows + index / cols])
+                        .collect();
+                }
+                field.values_mut().copy_from_slice(&values);
+                field.push();
+                Ok(())
+            })
+        };
+        match result {
+            Ok(()) => (),
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof && layer_len == 0 => {
+                return Ok(index)
+            }
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(Error::InvalidWeightsError {
+                    reason: format!("the file ends inside layer {}", index),
+                })
+            }
+            Err(error) => {
+                return Err(Error::InvalidWeightsError {
+                    reason: format!("failed to read layer {}: {}", index, error),
+                })
+            }
+        }
+    }
+
+    Ok(layers.len().min(cutoff))
+}
+
+/// A block of floats of a layer in a loaded network.
+struct Field {
+    values: *mut f32,
+    #[cfg_attr(not(feature = "enable-cuda"), allow(dead_code))]
+    values_gpu: *mut f32,
+    len: usize,
+    /// The (rows, columns) of connected layer weights, which old files store transposed.
+    matrix: Option<(usize, usize)>,
+    /// The (rows, columns) of convolutional weights with `flipped=1`, which are stored transposed.
+    flipped: Option<(usize, usize)>,
+}
+
+impl Field {
+    unsafe fn values(&self) -> &[f32] {
+        slice::from_raw_parts(self.values, self.len)
+    }
+
+    unsafe fn values_mut(&mut self) -> &mut [f32] {
+        slice::from_raw_parts_mut(self.values, self.len)
+    }
+
+    /// Copy the values from the GPU if the network runs there.
+    unsafe fn pull(&self) {
+        #[cfg(feature = "enable-cuda")]
+        if sys::gpu_index >= 0 && !self.values_gpu.is_null() {
+            sys::cuda_pull_array(self.values_gpu, self.values, self.len);
+        }
+    }
+
+    /// Copy the values to the GPU if the network runs there.
+    unsafe fn push(&self) {
+        #[cfg(feature = "enable-cuda")]
+        if sys::gpu_index >= 0 && !self.values_gpu.is_null() {
+            cuda_push_array(self.values_gpu, self.values, self.len);
+        }
+    }
+}
+
+#[cfg(feature = "enable-cuda")]
+extern "C" {
+    fn cuda_push_array(x_gpu: *mut f32, x: *mut f32, n: usize);
+}
+
+type Visitor<'a> = &'a mut dyn FnMut(Field) -> io::Result<()>;
+
+/// Visit the fields of a layer in file order, recursing into the sublayers of recurrent layers.
+///
+/// When \[loading\], batchnorm values of layers with `dontloadscales=1` are skipped.
+unsafe fn visit_layer(layer: &sys::layer, loading: bool, visit: Visitor) -> io::Result<()> {
+    let layer_type: Option<LayerType> = FromPrimitive::from_usize(layer.type_ as usize);
+    let field = |values, values_gpu, len: i32| Field {
+        values,
+        values_gpu,
+        len: len.max(0) as usize,
+        matrix: None,
+        flipped: None,
+    };
+
+    match layer_type {
+        Some(LayerType::Convolutional) if layer.share_layer.is_null() => {
+            visit_convolutional(layer, loading, visit)
+        }
+        Some(LayerType::Shortcut) | Some(LayerType::Implicit) if layer.nweights > 0 => {
+            visit(field(layer.weights, layer.weights_gpu, layer.nweights))
+        }
+        Some(LayerType::Connected) => visit_connected(layer, loading, visit),
+        Some(LayerType::Batchnorm) => {
+            visit(field(layer.biases, layer.biases_gpu, layer.c))?;
+            visit_batchnorm(layer, layer.c, visit)
+        }
+        Some(LayerType::Rnn) => [layer.input_layer, layer.self_layer, layer.output_layer]
+            .iter()
+            .try_for_each(|&sublayer| visit_connected(&*sublayer, loading, visit)),
+        Some(LayerType::Gru) => [
+            layer.input_z_layer,
+            layer.input_r_layer,
+            layer.input_h_layer,
+            layer.state_z_layer,
+            layer.state_r_layer,
+            layer.state_h_layer,
+        ]
+        .iter()
+        .try_for_each(|&sublayer| visit_connected(&*sublayer, loading, visit)),
+        Some(LayerType::Lstm) => [
+            layer.wf, layer.wi, layer.wg, layer.wo, layer.uf, layer.ui, layer.ug, layer.uo,
+        ]
+        .iter()
+        .try_for_each(|&sublayer| visit_connected(&*sublayer, loading, visit)),
+        Some(LayerType::ConvLstm) => {
+            let mut sublayers = vec![];
+            if layer.peephole != 0 {
+                sublayers.extend(&[layer.vf, layer.vi, layer.vo]);
+            }
+            sublayers.push(layer.wf);
+            if layer.bottleneck == 0 {
+                sublayers.extend(&[layer.wi, layer.wg, layer.wo]);
+            }
+            sublayers.extend(&[layer.uf, layer.ui, layer.ug, layer.uo]);
+            sublayers
+                .iter()
+                .try_for_each(|&sublayer| visit_convolutional(&*sublayer, loading, visit))
+        }
+        Some(LayerType::Crnn) => [layer.input_layer, layer.self_layer, layer.output_layer]
+            .iter()
+            .try_for_each(|&sublayer| visit_convolutional(&*sublayer, loading, visit)),
+        Some(LayerType::Local) => {
+            let locations = layer.out_w * layer.out_h;
+            let size = layer.size * layer.size * layer.c * layer.n * locations;
+            visit(field(layer.biases, layer.biases_gpu, layer.outputs))?;
+            visit(field(layer.weights, layer.weights_gpu, size))
+        }
+        _ => Ok(()),
+    }
+}
+
+unsafe fn visit_convolutional(layer: &sys::layer, loading: bool, visit: Visitor) -> io::Result<()> {
+    visit(Field {
+        values: layer.biases,
+        values_gpu: layer.biases_gpu,
+        len: layer.n.max(0) as usize,
+        matrix: None,
+        flipped: None,
+    })?;
+    if layer.batch_normalize != 0 && !(loading && layer.dontloadscales != 0) {
+        visit_batchnorm(layer, layer.n, visit)?;
+    }
+    let (n, groups) = (layer.n.max(0) as usize, layer.groups.max(1) as usize);
+    let filter_len = layer.c.max(0) as usize / groups * (layer.size * layer.size).max(0) as usize;
+    visit(Field {
+        values: layer.weights,
+        values_gpu: layer.weights_gpu,
+        len: layer.nweights.max(0) as usize,
+        matrix: None,
+        flipped: (layer.flipped != 0).then_some((n, filter_len)),
+    })
+}
+
+unsafe fn visit_connected(layer: &sys::layer, loading: bool, visit: Visitor) -> io::Result<()> {
+    let (outputs, inputs) = (layer.outputs.max(0) as usize, layer.inputs.max(0) as usize);
+    visit(Field {
+        values: layer.biases,
+        values_gpu: layer.biases_gpu,
+        len: outputs,
+        matrix: None,
+        flipped: None,
+    })?;
+    visit(Field {
+        values: layer.weights,
+        values_gpu: layer.weights_gpu,
+        len: outputs * inputs,
+        matrix: Some((outputs, inputs)),
+        flipped: None,
+    })?;
+    if layer.batch_normalize != 0 && !(loading && layer.dontloadscales != 0) {
+        visit_batchnorm(layer, layer.outputs, visit)?;
+    }
+    Ok(())
+}
+
+unsafe fn visit_batchnorm(layer: &sys::layer, n: i32, visit: Visitor) -> io::Result<()> {
+    let len = n.max(0) as usize;
+    [
+        (layer.scales, layer.scales_gpu),
+        (layer.rolling_mean, layer.rolling_mean_gpu),
+        (layer.rolling_variance, layer.rolling_variance_gpu),
+    ]
+    .iter()
+    .try_for_each(|&(values, values_gpu)| {
+        visit(Field {
+            values,
+            values_gpu,
+            len,
+            matrix: None,
+            flipped: None,
+        })
+    })
+}
+
+/// The names and shapes of the tensors of a layer, in file order.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LayerLayout {
+    pub index: usize,
+    pub layer_type: LayerType,
+    pub tensors: Vec<(&'static str, Vec<usize>)>,
+}
+
+impl LayerLayout {
+    /// Get the number of floats of the layer.
+    pub fn num_floats(&self) -> usize {
+        self.tensors
+            .iter()
+            .map(|(_, shape)| shape.iter().product::<usize>())
+            .sum()
+    }
+}
+
+/// Compute the tensors stored for each layer with weights in \[config\].
+pub(crate) fn layout(config: &Config, transposed: bool) -> Result<Vec<LayerLayout>, Error> {
+    let input_shape = config.input_shape()?;
+    let shapes = config.output_shapes()?;
+    let mut layout = vec![];
+
+    for (index, layer) in config.layers.iter().enumerate() {
+        let options = layer.options();
+        if options.get_parsed("dontload")?.unwrap_or(0) != 0 {
+            continue;
+        }
+        let (w, h, c) = match index {
+            0 => input_shape,
+            _ => shapes[index - 1],
+        };
+        let (_, _, out_c) = shapes[index];
+        let load_scales = options.get_parsed("dontloadscales")?.unwrap_or(0) == 0;
+        let batchnorm = |n: usize| {
+            vec![
+                ("scales", vec![n]),
+                ("rolling_mean", vec![n]),
+                ("rolling_variance", vec![n]),
+            ]
+        };
+
+        let tensors = match layer {
+            LayerConfig::Convolutional(conv) => {
+                if options.get("share_index").is_some() {
+                    continue;
+                }
+                let n = conv.filters.unwrap_or(1);
+                let size = conv.size.unwrap_or(1);
+                let groups = conv.groups.unwrap_or(1).max(1);
+                let mut tensors = vec![("biases", vec![n])];
+                if conv.batch_normalize.unwrap_or(false) && load_scales {
+                    tensors.extend(batchnorm(n));
+                }
+                let shape = if options.get_parsed("flipped")?.unwrap_or(0) != 0 {
+                    vec![c / groups * size * size, n]
+                } else {
+                    vec![n, c / groups, size, size]
+                };
+                tensors.push(("weights", shape));
+                tensors
+            }
+            LayerConfig::Connected(connected) => {
+                let outputs = connected.output.unwrap_or(1);
+                let inputs = w * h * c;
+                let shape = if transposed {
+                    vec![inputs, outputs]
+                } else {
+                    vec![outputs, inputs]
+                };
+                let mut tensors = vec![("biases", vec![outputs]), ("weights", shape)];
+                if connected.batch_normalize.unwrap_or(false) && load_scales {
+                    tensors.extend(batchnorm(outputs));
+                }
+                tensors
+            }
+            LayerConfig::Shortcut(shortcut) => {
+                let n = shortcut.from.as_ref().map_or(1, Vec::len);
+                match options.get("weights_type").unwrap_or("none") {
+                    "per_feature" | "per_layer" => vec![("weights", vec![n + 1])],
+                    "per_channel" => vec![("weights", vec![n + 1, out_c])],
+                    _ => continue,
+                }
+            }
+            LayerConfig::Other(other) => match other.layer_type {
+                LayerType::Batchnorm => {
+                    let mut tensors = vec![("biases", vec![c])];
+                    tensors.extend(batchnorm(c));
+                    tensors
+                }
+                LayerType::Implicit => vec![("weights", vec![shapes[index].1, out_c])],
+                LayerType::Rnn
+                | LayerType::Gru
+                | LayerType::Lstm
+                | LayerType::Crnn
+                | LayerType::ConvLstm
+                | LayerType::Local => {
+                    return Err(Error::InvalidConfigError {
+                        reason: format!(
+                            "weights of [{}] layers are not supported",
+                            other.layer_type.name()
+                        ),
+                    })
+                }
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        layout.push(LayerLayout {
+            index,
+            layer_type: layer.layer_type(),
+            tensors,
+        });
+    }
+
+    Ok(layout)
+}
+
+/// Check that a weights file can be opened and has a complete header.
+///
+/// If the \[config\] layout is known, the file must also not end inside a layer. Data after the
+/// last layer is allowed, since libdarknet stops reading there, e.g. for full weights loaded into
+/// a backbone config.
+pub(crate) fn check_file(path: &Path, config: Option<&Config>) -> Result<(), Error> {
+    let open_error = |error| Error::WeightsOpenError {
+        path: path.to_owned(),
+        error,
+    };
+    let mut file = File::open(path).map_err(open_error)?;
+    let file_len = file.metadata().map_err(open_error)?.len();
+
+    let mut version = [0u8; 12];
+    match file.read_exact(&mut version) {
+        Ok(()) => (),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+            return Err(Error::InvalidWeightsError {
+                reason: format!("the file has {} bytes, too short for a header", file_len),
+            })
+        }
+        Err(err) => return Err(open_error(err)),
+    }
+    let payload_len = check_len(&version, file_len)?;
+
+    // the config is checked leniently elsewhere, so layouts that cannot be computed are skipped
+    let major = i32::from_le_bytes([version[0], version[1], version[2], version[3]]);
+    let minor = i32::from_le_bytes([version[4], version[5], version[6], version[7]]);
+    let transposed = major > 1000 || minor > 1000;
+    if let Some(layout) = config.and_then(|config| layout(config, transposed).ok()) {
+        check_layout(&layout, payload_len)?;
+    }
+
+    Ok(())
+}
+
+/// Check the file length against the version words at the start of the header, and return the
+/// length of the payload.
+fn check_len(version: &[u8; 12], file_len: u64) -> Result<u64, Error> {
+    let major = i32::from_le_bytes([version[0], version[1], version[2], version[3]]);
+    let minor = i32::from_le_bytes([version[4], version[5], version[6], version[7]]);
+
+    let header_len = header_len(major, minor);
+    if file_len < header_len {
+        return Err(Error::InvalidWeightsError {
+            reason: format!("the file has {} bytes, too short for a header", file_len),
+        });
+    }
+    if !(file_len - header_len).is_multiple_of(4) {
+        return Err(Error::InvalidWeightsError {
+            reason: format!(
+                "the payload of {} bytes is not a whole number of floats",
+                file_len - header_len
+            ),
+        });
+    }
+
+    Ok(file_len - header_len)
+}
+
+/// Check that a payload of \[payload_len\] bytes does not end inside a layer of \[layout\].
+fn check_layout(layout: &[LayerLayout], payload_len: u64) -> Result<(), Error> {
+    let mut offset = 0u64;
+    for layer in layout {
+        if offset >= payload_len {
+            return Ok(());
+        }
+        offset += layer.num_floats() as u64 * 4;
+        if offset > payload_len {
+            return Err(Error::InvalidWeightsError {
+                reason: format!(
+                    "the file ends inside layer {}, the config expects at least {} bytes of weights",
+                    layer.index, offset
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The `seen` counter is 64-bit since version 0.2.
+fn seen_is_64bit(major: i32, minor: i32) -> bool {
+    i64::from(major) * 10 + i64::from(minor) >= 2
+}
+
+fn header_len(major: i32, minor: i32) -> u64 {
+    if seen_is_64bit(major, minor) {
+        20
+    } else {
+        16
+    }
+}
+
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8], what: &str) -> Result<(), Error> {
+    reader.read_exact(buf).map_err(|error| match error.kind() {
+        io::ErrorKind::UnexpectedEof => Error::InvalidWeightsError {
+            reason: format!("the file ends inside {}", what),
+        },
+        _ => Error::InvalidWeightsError {
+            reason: format!("failed to read {}: {}", what, error),
+        },
+    })
+}
+
+/// Read until \[buf\] is full or the stream ends, and return the number of bytes read.
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, Error> {
+    let mut count = 0;
+    while count < buf.len() {
+        match reader.read(&mut buf[count..]) {
+            Ok(0) => break,
+            Ok(n) => count += n,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+            Err(error) => {
+                return Err(Error::InvalidWeightsError {
+                    reason: format!("failed to read the file: {}", error),
+                })
+            }
+        }
+    }
+    Ok(count)
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use common::write_temp;
+use darknet::{cfg::Config, weights::Weights, Detections, Error, Network, NmsKind, PredictOptions};
+use image::{Rgb, RgbImage};
+use std::{env, fs, path::PathBuf};
+
+const CFG: &str = r#"
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=8
+size=3
+stride=2
+pad=1
+activation=leaky
+
+[convolutional]
+filters=18
+size=1
+stride=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=4,4, 8,8, 16,16
+classes=1
+num=3
+"#;
+
+fn images() -> Vec<RgbImage> {
+    [(40, 20), (20, 40), (32, 32)]
+        .iter()
+        .map(|&(width, height)| {
+            RgbImage::from_fn(width, height, |x, y| {
+                Rgb([
+                    (x * 7 % 256) as u8,
+                    (y * 13 % 256) as u8,
+                    ((x + y) * 5 % 256) as u8,
+                ])
+            })
+        })
+        .collect()
+}
+
+/// Write the config and random weights with a unit rolling variance, so that the batch
+/// normalization keeps the activations finite.
+fn files(name: &str) -> Result<(PathBuf, PathBuf), Error> {
+    let cfg = write_temp(&format!("{}.cfg", name), CFG);
+    let mut bytes = vec![];
+    Network::load(&cfg, None::<&str>, false)?.write_weights(&mut bytes)?;
+    let mut weights = Weights::from_reader(&bytes[..], &CFG.parse::<Config>()?)?;
+    for tensor in &mut weights.layers[0].tensors {
+        if tensor.name == "rolling_variance" {
+            tensor.data.iter_mut().for_each(|value| *value = 1.0);
+        }
+    }
+    let path = env::temp_dir().join(format!("darknet-rust-{}.weights", name));
+    weights.save(&path)?;
+    Ok((cfg, path))
+}
+
+fn assert_same(batched: &Detections, single: &Detections) {
+    assert_eq!(batched.len(), single.len());
+    for (a, b) in batched.iter().zip(single.iter()) {
+        let (a_box, b_box) = (a.bbox(), b.bbox());
+        let diffs = [
+            a_box.x - b_box.x,
+            a_box.y - b_box.y,
+            a_box.w - b_box.w,
+            a_box.h - b_box.h,
+            a.objectness() - b.objectness(),
+        ];
+        assert!(diffs.iter().all(|diff| diff.abs() < 1e-5), "{:?}", diffs);
+        assert_eq!(a.probabilities(), b.probabilities());
+    }
+}
+
+#[test]
+fn predict_batch_matches_single_predictions() -> Result<(), Error> {
+    let (cfg, weights) = files("batch")?;
+    let mut net = Network::load_with_batch(&cfg, Some(&weights), false, 2)?;
+    assert_eq!(net.max_batch(), 2);
+
+    let images = images();
+    for &use_letter_box in &[false, true] {
+        let batched = net.predict_batch(&images, 0.2, 0.5, 0.45, use_letter_box)?;
+        assert_eq!(batched.len(), images.len());
+        for (image, batched) in images.iter().zip(&batched) {
+            let single = net.predict(image, 0.2, 0.5, 0.45, use_letter_box);
+            assert!(!single.is_empty());
+            assert_same(batched, &single);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn predict_batch_with_options_matches_single_predictions() -> Result<(), Error> {
+    let (cfg, weights) = files("batch-options")?;
+    let mut net = Network::load_with_batch(&cfg, Some(&weights), false, 2)?;
+
+    let images = images();
+    let options = PredictOptions::new()
+        .thresh(0.2)
+        .nms_kind(NmsKind::Diou)
+        .relative(false)
+        .classes(vec![0])
+        .max_detections(2);
+    let batched = net.predict_batch_with(&images, &options)?;
+    assert_eq!(batched.len(), images.len());
+    for (image, batched) in images.iter().zip(&batched) {
+        let single = net.predict_with(image, &options);
+        assert!(!single.is_empty() && single.len() <= 2);
+        assert_same(batched, &single);
+    }
+    Ok(())
+}
+
+#[test]
+fn predict_batch_rejects_other_heads() -> Result<(), Error> {
+    let cfg = CFG.replace("[yolo]", "[region]");
+    let mut net = Network::from_cfg_str(&cfg, None::<&str>, false)?;
+    let result = net.predict_batch(&images(), 0.2, 0.5, 0.45, true);
+    assert!(matches!(result, Err(Error::InvalidConfigError { .. })));
+    Ok(())
+}
+
+#[test]
+fn load_with_batch_keeps_weights_layout() -> Result<(), Error> {
+    let (cfg, path) = files("batch-layout")?;
+    let weights = fs::read(&path).unwrap();
+
+    // the batch normalization is not fused into the convolution, so it is written back
+    let mut net = Network::load_with_batch(&cfg, Some(&path), false, 2)?;
+    let mut written = vec![];
+    net.write_weights(&mut written)?;
+    assert_eq!(written, weights);
+    assert_eq!(net.load_weights_upto(&path, usize::MAX)?, 3);
+    Ok(())
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::assert_close;
+use darknet::{BBox, BoundingBox};
+
+#[test]
+fn convert_formats() {
+    let bbox = BoundingBox::from_xyxy(10.0, 20.0, 50.0, 40.0);
+    assert_eq!(bbox, BoundingBox::new(30.0, 30.0, 40.0, 20.0));
+    assert_eq!(bbox.to_xywh(), [10.0, 20.0, 40.0, 20.0]);
+    assert_eq!(BoundingBox::from_xywh(10.0, 20.0, 40.0, 20.0), bbox);
+    assert_eq!(bbox.to_xyxy(), [10.0, 20.0, 50.0, 40.0]);
+
+    let normalized = bbox.to_normalized(128, 64);
+    assert_eq!(normalized.to_cxcywh(), [0.234375, 0.46875, 0.3125, 0.3125]);
+    assert_eq!(normalized.to_pixels(128, 64), bbox);
+
+    let raw = BBox {
+        x: 0.5,
+        y: 0.25,
+        w: 0.125,
+        h: 1.0,
+    };
+    let converted = BBox::from(BoundingBox::from(raw));
+    assert_eq!(
+        (converted.x, converted.y, converted.w, converted.h),
+        (raw.x, raw.y, raw.w, raw.h)
+    );
+}
+
+#[test]
+fn clamp_to_image() {
+    let bbox = BoundingBox::from_xyxy(-0.25, 0.5, 0.5, 1.5).clamp(1.0, 1.0);
+    assert_eq!(bbox.to_xyxy(), [0.0, 0.5, 0.5, 1.0]);
+
+    // a box outside the image collapses to an empty box on the border
+    let bbox = BoundingBox::from_xyxy(120.0, 10.0, 140.0, 30.0).clamp(100.0, 80.0);
+    assert_eq!(bbox.to_xyxy(), [100.0, 10.0, 100.0, 30.0]);
+    assert_eq!(bbox.area(), 0.0);
+}
+
+#[test]
+fn overlap_metrics() {
+    let a = BoundingBox::from_xyxy(0.0, 0.0, 2.0, 2.0);
+    let b = BoundingBox::from_xyxy(1.0, 1.0, 3.0, 3.0);
+    assert_eq!(a.intersection(&b), 1.0);
+    assert_eq!(a.union(&b), 7.0);
+    assert_close(a.iou(&b), 1.0 / 7.0);
+    // the enclosing box is 3x3
+    assert_close(a.giou(&b), 1.0 / 7.0 - 2.0 / 9.0);
+    // the centers are sqrt(2) apart and the enclosing diagonal is sqrt(18)
+    assert_close(a.diou(&b), 1.0 / 7.0 - 2.0 / 18.0);
+    // same aspect ratios, so CIoU equals DIoU
+    assert_close(a.ciou(&b), a.diou(&b));
+
+    let c = BoundingBox::from_xyxy(0.0, 0.0, 4.0, 1.0);
+    let aspect =
+        4.0 / (std::f32::consts::PI * std::f32::consts::PI) * (1f32.atan() - 4f32.atan()).powi(2);
+    let iou = a.iou(&c);
+    assert_close(iou, 2.0 / 6.0);
+    let ciou = iou - (1.25 / 20.0 + aspect / (1.0 - iou + aspect + 0.000001) * aspect);
+    assert_close(c.ciou(&a), ciou);
+
+    let far = BoundingBox::from_xyxy(10.0, 10.0, 11.0, 11.0);
+    assert_eq!(a.iou(&far), 0.0);
+    assert!(a.giou(&far) < 0.0);
+    assert_eq!(a.iou(&a), 1.0);
+    assert_eq!(a.giou(&a), 1.0);
+    assert_eq!(a.diou(&a), 1.0);
+}
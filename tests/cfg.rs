@@ -0,0 +1,177 @@
+use darknet::{
+    cfg::{Config, LayerConfig},
+    Activation, Error, LayerType,
+};
+
+const CFG: &str = "\
+[net]
+# Testing
+batch = 1
+subdivisions=1
+width=416
+height=416
+channels=3
+mosaic=1
+
+[conv]
+batch_normalize=1
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[maxpool]
+size=2
+stride=2
+
+[route]
+layers = -1, 0 ### P3
+
+[convolutional]
+size=1
+stride=1
+pad=1
+filters=255
+activation=linear
+
+[yolo]
+mask = 3,4,5
+anchors = 10,14,  23,27,  37,58,  81,82,  135,169,  344,319
+classes=80
+num=6
+nms_kind=greedynms
+ignore_thresh = .7
+
+[upsample]
+stride=2
+
+[dropout]
+probability=.5
+";
+
+#[test]
+fn parse_typed_sections() -> Result<(), Error> {
+    let config: Config = CFG.parse()?;
+
+    assert_eq!(config.net.batch, Some(1));
+    assert_eq!(config.net.width, Some(416));
+    assert_eq!(config.net.options.get("mosaic"), Some("1"));
+
+    let types = config
+        .layers
+        .iter()
+        .map(LayerConfig::layer_type)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        [
+            LayerType::Convolutional,
+            LayerType::Maxpool,
+            LayerType::Route,
+            LayerType::Convolutional,
+            LayerType::Yolo,
+            LayerType::Upsample,
+            LayerType::Dropout,
+        ]
+    );
+
+    match &config.layers[0] {
+        LayerConfig::Convolutional(conv) => {
+            assert_eq!(conv.filters, Some(16));
+            assert_eq!(conv.batch_normalize, Some(true));
+            assert_eq!(conv.activation, Some(Activation::Leaky));
+        }
+        _ => unreachable!(),
+    }
+    match &config.layers[2] {
+        LayerConfig::Route(route) => assert_eq!(route.layers, Some(vec![-1, 0])),
+        _ => unreachable!(),
+    }
+    match &config.layers[4] {
+        LayerConfig::Yolo(yolo) => {
+            assert_eq!(yolo.mask, Some(vec![3, 4, 5]));
+            assert_eq!(yolo.anchors.as_ref().map(Vec::len), Some(6));
+            assert_eq!(yolo.anchors.as_ref().unwrap()[1], (23.0, 27.0));
+            assert_eq!(yolo.ignore_thresh, Some(0.7));
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(config.layers[6].options().get("probability"), Some(".5"));
+
+    Ok(())
+}
+
+#[test]
+fn parse_errors_report_lines() {
+    let text = CFG.replace("filters=255", "filters=two");
+    match text.parse::<Config>() {
+        Err(Error::ConfigParseError { line, .. }) => assert_eq!(line, 29),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let text = CFG.replace("[maxpool]", "[maxpooling]");
+    match text.parse::<Config>() {
+        Err(Error::ConfigParseError { line, .. }) => assert_eq!(line, 18),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let text = CFG.replace("activation=leaky", "activation=");
+    match text.parse::<Config>() {
+        Err(Error::ConfigParseError { line, .. }) => assert_eq!(line, 16),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert!(matches!(
+        "batch=1\n[net]".parse::<Config>(),
+        Err(Error::ConfigParseError { line: 1, .. })
+    ));
+    assert!(matches!(
+        "[yolo]\nclasses=1".parse::<Config>(),
+        Err(Error::InvalidNetSectionError { .. })
+    ));
+}
+
+#[test]
+fn write_and_parse_back() -> Result<(), Error> {
+    let mut config: Config = CFG.parse()?;
+    config.net.width = Some(608);
+    config.net.height = Some(608);
+    if let LayerConfig::Yolo(yolo) = &mut config.layers[4] {
+        yolo.classes = Some(2);
+    }
+
+    let text = config.to_string();
+    assert!(text.starts_with("[net]\nbatch=1\n"));
+    assert!(text.contains("\n[convolutional]\nbatch_normalize=1\nfilters=16\n"));
+    assert!(text.contains("\nactivation=leaky\n"));
+    assert!(text.contains("\nnms_kind=greedynms\n"));
+
+    let parsed: Config = text.parse()?;
+    assert_eq!(parsed, config);
+    assert_eq!(parsed.net.width, Some(608));
+    Ok(())
+}
+
+#[test]
+fn retarget_classes() -> Result<(), Error> {
+    let mut config: Config = CFG.parse()?;
+    config.retarget_classes(2)?;
+
+    match (&config.layers[3], &config.layers[4]) {
+        (LayerConfig::Convolutional(conv), LayerConfig::Yolo(yolo)) => {
+            assert_eq!(conv.filters, Some((2 + 5) * 3));
+            assert_eq!(yolo.classes, Some(2));
+        }
+        _ => unreachable!(),
+    }
+
+    let mut config: Config = CFG.replace("mask = 3,4,5", "mask = 5,6").parse()?;
+    let original = config.clone();
+    assert!(matches!(
+        config.retarget_classes(2),
+        Err(Error::InvalidConfigError { .. })
+    ));
+    assert_eq!(config, original);
+    Ok(())
+}
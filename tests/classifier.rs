@@ -0,0 +1,85 @@
+use darknet::{Classification, Classifier, Error, Network};
+use image::{GrayImage, Luma};
+use std::{env, fs};
+
+const CFG: &str = r#"
+[net]
+width=4
+height=4
+channels=1
+
+[avgpool]
+
+[connected]
+output=3
+activation=linear
+
+[softmax]
+"#;
+
+fn network() -> Result<Network, Error> {
+    // logits = [x, 0.5, -x] for the mean pixel value x
+    let mut weights = vec![];
+    [0i32, 2, 0]
+        .iter()
+        .for_each(|word| weights.extend(&word.to_le_bytes()));
+    weights.extend(&0u64.to_le_bytes());
+    [0.0f32, 0.5, 0.0, 1.0, 0.0, -1.0]
+        .iter()
+        .for_each(|value| weights.extend(&value.to_le_bytes()));
+    Network::from_bytes(CFG.as_bytes(), Some(&weights), false)
+}
+
+fn labels() -> Vec<String> {
+    vec!["bright".into(), "gray".into(), "dark".into()]
+}
+
+#[test]
+fn classify_top_k() -> Result<(), Error> {
+    let mut classifier = Classifier::new(network()?, labels())?;
+
+    let bright = GrayImage::from_pixel(8, 6, Luma([255]));
+    let top = classifier.classify(&bright, 2)?;
+    let labels: Vec<_> = top.iter().map(|class| class.label.as_str()).collect();
+    assert_eq!(labels, ["bright", "gray"]);
+    let sum: f32 = classifier.probabilities(&bright)?.iter().sum();
+    assert!((sum - 1.0).abs() < 1e-5);
+
+    let black = GrayImage::from_pixel(5, 9, Luma([0]));
+    let Classification {
+        index,
+        label,
+        probability,
+    } = classifier.classify(&black, 1)?.remove(0);
+    assert_eq!((index, label.as_str()), (1, "gray"));
+    let expected = 0.5f32.exp() / (2.0 + 0.5f32.exp());
+    assert!((probability - expected).abs() < 1e-5);
+
+    assert_eq!(classifier.classify(&black, 10)?.len(), 3);
+    Ok(())
+}
+
+#[test]
+fn load_with_label_file() -> Result<(), Error> {
+    let dir = env::temp_dir();
+    let cfg = dir.join("darknet-rust-classifier.cfg");
+    let names = dir.join("darknet-rust-classifier.names");
+    fs::write(&cfg, CFG).unwrap();
+    fs::write(&names, "bright\ngray\ndark\n").unwrap();
+    let weights = dir.join("darknet-rust-classifier.weights");
+    network()?.save_weights(&weights)?;
+
+    let classifier = Classifier::load(&cfg, &weights, &names)?;
+    assert_eq!(classifier.labels(), labels().as_slice());
+    Ok(())
+}
+
+#[test]
+fn label_count_mismatch() -> Result<(), Error> {
+    let result = Classifier::new(network()?, vec!["bright".into()]);
+    match result {
+        Err(Error::LabelCountError { labels, classes }) => assert_eq!((labels, classes), (1, 3)),
+        _ => panic!("expect a label count error"),
+    }
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! Fixtures shared by the integration tests.
+
+// each test crate uses a part of the fixtures
+#![allow(dead_code)]
+
+use darknet::{BoundingBox, OwnedDetection};
+use std::{env, fs, path::PathBuf};
+
+/// A detection of one of 2 classes.
+pub fn detection(bbox: BoundingBox, class: usize, score: f32) -> OwnedDetection {
+    let mut probabilities = vec![0.0; 2];
+    probabilities[class] = score;
+    let mut detection = OwnedDetection::new(bbox, probabilities);
+    detection.sort_class = class;
+    detection
+}
+
+/// Write a file in the temporary directory, where the name is prefixed by `darknet-rust-`.
+pub fn write_temp<C: AsRef<[u8]>>(name: &str, contents: C) -> PathBuf {
+    let path = env::temp_dir().join(format!("darknet-rust-{}", name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+pub fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1e-5,
+        "expected {}, found {}",
+        expected,
+        actual
+    );
+}
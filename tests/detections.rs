@@ -0,0 +1,85 @@
+use darknet::{BBox, Error, Network, OwnedDetection};
+use image::{Rgb, RgbImage};
+use std::thread;
+
+const CFG: &str = r#"
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=3
+stride=4
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=4,4, 8,8, 16,16
+classes=1
+num=3
+"#;
+
+fn detect() -> Result<darknet::Detections, Error> {
+    let mut net = Network::from_cfg_str(CFG, None::<&str>, false)?;
+    let image = RgbImage::from_fn(48, 32, |x, y| Rgb([(x * 5) as u8, (y * 7) as u8, 128]));
+    Ok(net.predict(&image, 0.1, 0.5, 0.45, true))
+}
+
+#[test]
+fn owned_detections() -> Result<(), Error> {
+    let detections = detect()?;
+    assert!(!detections.is_empty());
+
+    let owned = detections.to_owned_vec();
+    assert_eq!(owned.len(), detections.len());
+    for (owned, detection) in owned.iter().zip(detections.iter()) {
+        let (a, b) = (owned.bbox, *detection.bbox());
+        assert_eq!((a.x, a.y, a.w, a.h), (b.x, b.y, b.w, b.h));
+        assert_eq!(owned.objectness, detection.objectness());
+        assert_eq!(owned.probabilities, detection.probabilities());
+        assert_eq!(owned.num_classes(), 1);
+        assert_eq!(owned.best_class(None), detection.best_class(None));
+        assert_eq!(owned.best_class(Some(1.0)), None);
+        assert_eq!(owned.uc, None);
+    }
+    Ok(())
+}
+
+#[test]
+fn new_owned_detection() {
+    let bbox = BBox {
+        x: 0.5,
+        y: 0.5,
+        w: 0.25,
+        h: 0.5,
+    };
+    let detection = OwnedDetection::new(bbox, vec![0.25, 0.75, 0.5]);
+    assert_eq!(
+        (
+            detection.bbox.x,
+            detection.bbox.y,
+            detection.bbox.w,
+            detection.bbox.h
+        ),
+        (0.5, 0.5, 0.25, 0.5)
+    );
+    assert_eq!(detection.objectness, 0.75);
+    assert_eq!(detection.sort_class, 1);
+    assert_eq!(detection.uc, None);
+    assert_eq!(detection.best_class(None), Some((1, 0.75)));
+}
+
+#[test]
+fn send_detections_by_value() -> Result<(), Error> {
+    let detections = detect()?;
+    let count = detections.len();
+
+    let owned: Vec<OwnedDetection> = thread::spawn(move || detections.into_iter().collect())
+        .join()
+        .unwrap();
+    assert_eq!(owned.len(), count);
+    Ok(())
+}
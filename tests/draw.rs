@@ -0,0 +1,174 @@
+use darknet::{class_color, BBox, Error, Image, Labels, Network, PredictOptions};
+use darknet_sys as sys;
+use image::{Rgb, RgbImage};
+use std::{
+    ffi::CString,
+    os::raw::{c_char, c_int},
+};
+
+extern "C" {
+    fn get_color(c: c_int, x: c_int, max: c_int) -> f32;
+    fn draw_box_width(
+        a: sys::image,
+        x1: c_int,
+        y1: c_int,
+        x2: c_int,
+        y2: c_int,
+        w: c_int,
+        r: f32,
+        g: f32,
+        b: f32,
+    );
+    fn load_alphabet() -> *mut *mut sys::image;
+    fn draw_detections_v3(
+        im: sys::image,
+        dets: *mut sys::detection,
+        num: c_int,
+        thresh: f32,
+        names: *mut *mut c_char,
+        alphabet: *mut *mut sys::image,
+        classes: c_int,
+        ext_output: c_int,
+    );
+}
+
+const CFG: &str = r#"
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=21
+size=3
+stride=4
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=4,4, 8,8, 16,16
+classes=2
+num=3
+"#;
+
+fn image(width: u32, height: u32) -> Image {
+    RgbImage::from_fn(width, height, |x, y| {
+        Rgb([(x % 251) as u8, (y % 241) as u8, ((x + y) % 239) as u8])
+    })
+    .into()
+}
+
+fn assert_images_close(actual: &Image, expected: &Image) {
+    let mismatches = actual
+        .get_data()
+        .iter()
+        .zip(expected.get_data())
+        .filter(|(a, b)| (*a - *b).abs() > 1e-5)
+        .count();
+    assert_eq!(mismatches, 0);
+}
+
+#[test]
+fn match_darknet_colors() {
+    for &classes in &[1, 2, 20, 80] {
+        for class in 0..classes {
+            let expected = unsafe {
+                let offset = (class * 123457 % classes) as c_int;
+                [2, 1, 0].map(|channel| get_color(channel, offset, classes as c_int))
+            };
+            assert_eq!(class_color(class, classes), expected);
+        }
+    }
+}
+
+#[test]
+fn match_darknet_box() {
+    let bbox = BBox {
+        x: 0.125,
+        y: 0.5,
+        w: 0.5,
+        h: 0.25,
+    };
+    let mut drawn = image(200, 100);
+    drawn.draw_box(bbox, 3, [1.0, 0.5, 0.0]);
+
+    let mut expected = image(200, 100);
+    unsafe { draw_box_width(*expected.as_raw_mut(), 0, 37, 75, 62, 3, 1.0, 0.5, 0.0) };
+    assert_images_close(&drawn, &expected);
+    assert!(drawn.get_data() != image(200, 100).get_data());
+}
+
+#[test]
+fn match_darknet_detections() -> Result<(), Error> {
+    let mut net = Network::from_cfg_str(CFG, None::<&str>, false)?;
+    // the height gives boxes of 2 pixels wide and glyphs of size 2
+    let input = image(900, 1100);
+    let detections = net.predict_with(&input, &PredictOptions::new().thresh(0.05));
+    assert!(detections.len() > 2);
+    let labels = Labels::new(vec!["cat".to_owned(), "dog".to_owned()]);
+
+    let mut drawn = input.clone();
+    drawn.draw_detections(&detections, &labels);
+
+    let mut expected = input.clone();
+    let mut dets: Vec<sys::detection> = detections
+        .iter()
+        .map(|detection| {
+            let mut det: sys::detection = unsafe { std::mem::zeroed() };
+            det.bbox = *detection.bbox();
+            det.classes = 2;
+            det.prob = detection.probabilities().as_ptr() as *mut f32;
+            det.objectness = detection.objectness();
+            det
+        })
+        .collect();
+    let names: Vec<CString> = labels
+        .iter()
+        .map(|label| CString::new(label.as_str()).unwrap())
+        .collect();
+    let mut names: Vec<*mut c_char> = names.iter().map(|name| name.as_ptr() as *mut _).collect();
+    unsafe {
+        // libdarknet loads the glyphs from data/labels in the working directory
+        draw_detections_v3(
+            *expected.as_raw_mut(),
+            dets.as_mut_ptr(),
+            dets.len() as c_int,
+            0.0,
+            names.as_mut_ptr(),
+            load_alphabet(),
+            2,
+            0,
+        );
+    }
+    assert_images_close(&drawn, &expected);
+    assert!(drawn.get_data() != input.get_data());
+    Ok(())
+}
+
+#[test]
+fn draw_label_on_edges() {
+    let mut drawn = image(64, 48);
+    drawn.draw_label("é?", 60, 0, [0.0, 1.0, 0.0]);
+    drawn.draw_label("", 0, 0, [0.0, 1.0, 0.0]);
+    // the label does not fit above the first row, so it is drawn below it
+    let unchanged = image(64, 48);
+    let (data, original) = (drawn.get_data(), unchanged.get_data());
+    assert_eq!(data[..60], original[..60]);
+    assert!(data[60..64] != original[60..64]);
+
+    let mut gray = Image::zeros(64, 48, 1);
+    gray.draw_box(
+        BBox {
+            x: 0.5,
+            y: 0.5,
+            w: 2.0,
+            h: 2.0,
+        },
+        1,
+        [0.8, 0.0, 0.0],
+    );
+    let data = gray.get_data();
+    assert_eq!((data[0], data[63], data[64 * 47]), (0.8, 0.8, 0.8));
+    assert_eq!(data[65], 0.0);
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use common::{assert_close, detection, write_temp};
+use darknet::{
+    eval::{ApMethod, EvalOptions, Evaluator, GroundTruth},
+    BoundingBox, Error,
+};
+
+/// Two images with 4 ground truth objects of 2 classes.
+fn evaluator() -> Evaluator {
+    let a = BoundingBox::new(0.25, 0.25, 0.2, 0.2);
+    let b = BoundingBox::new(0.75, 0.75, 0.2, 0.2);
+    let c = BoundingBox::new(0.5, 0.5, 0.4, 0.4);
+    let d = BoundingBox::new(0.5, 0.5, 0.2, 0.2);
+
+    let mut evaluator = Evaluator::new();
+    evaluator.add_image(
+        &[
+            GroundTruth::new(0, a),
+            GroundTruth::new(0, b),
+            GroundTruth::new(1, c),
+        ],
+        &[
+            detection(a, 0, 0.9),
+            // a duplicate of the first detection
+            detection(BoundingBox::new(0.27, 0.25, 0.2, 0.2), 0, 0.8),
+            detection(BoundingBox::new(0.9, 0.1, 0.1, 0.1), 0, 0.7),
+            // the IoU with the ground truth is 0.7778
+            detection(BoundingBox::new(0.55, 0.5, 0.4, 0.4), 1, 0.6),
+            // below the confidence threshold
+            detection(b, 0, 0.2),
+        ],
+    );
+    evaluator.add_image(&[GroundTruth::new(1, d)], &[]);
+    evaluator
+}
+
+#[test]
+fn parse_ground_truth() -> Result<(), Error> {
+    let truths = GroundTruth::parse("0 0.5 0.5 0.2 0.4\n\n12 0.1 0.2 0.3 0.4 \n")?;
+    assert_eq!(
+        truths,
+        [
+            GroundTruth::new(0, BoundingBox::new(0.5, 0.5, 0.2, 0.4)),
+            GroundTruth::new(12, BoundingBox::new(0.1, 0.2, 0.3, 0.4)),
+        ]
+    );
+
+    let result = GroundTruth::parse("0 0.5 0.5 0.2 0.4\n1 0.5 0.5 0.2\n");
+    assert!(matches!(
+        result,
+        Err(Error::GroundTruthParseError { line: 2, .. })
+    ));
+    let result = GroundTruth::parse("x 0.5 0.5 0.2 0.4\n");
+    assert!(matches!(
+        result,
+        Err(Error::GroundTruthParseError { line: 1, .. })
+    ));
+
+    let path = write_temp("eval-image.txt", "3 0.5 0.5 0.2 0.4\n");
+    assert_eq!(GroundTruth::open(&path)?[0].class, 3);
+    let result = GroundTruth::open("./no-such-dir/image.txt");
+    assert!(matches!(result, Err(Error::GroundTruthOpenError { .. })));
+    Ok(())
+}
+
+#[test]
+fn average_precision_methods() {
+    // the ranks of class 0 are TP, FP, FP, TP with 2 ground truth objects
+    let precisions = [1.0, 0.5, 1.0 / 3.0, 0.5];
+    let recalls = [0.5, 0.5, 0.5, 1.0];
+    let ap = |method: ApMethod| method.average_precision(&precisions, &recalls);
+    assert!((ap(ApMethod::AllPoint) - 0.75).abs() < 1e-12);
+    assert!((ap(ApMethod::Voc11Point) - 8.5 / 11.0).abs() < 1e-12);
+    assert!((ap(ApMethod::Coco101Point) - 76.0 / 101.0).abs() < 1e-12);
+    assert_eq!(ApMethod::AllPoint.average_precision(&[], &[]), 0.0);
+}
+
+#[test]
+fn evaluate_detections() {
+    let evaluator = evaluator();
+    assert_eq!((evaluator.num_images(), evaluator.num_classes()), (2, 2));
+    let evaluation = evaluator.evaluate(&EvalOptions::default());
+
+    let [first, second] = [&evaluation.classes[0], &evaluation.classes[1]];
+    assert_eq!((first.num_truths, first.num_detections), (2, 4));
+    assert_eq!((second.num_truths, second.num_detections), (2, 1));
+    assert_close(first.ap, 0.75);
+    assert_close(second.ap, 0.5);
+    assert_close(evaluation.map, 0.625);
+
+    // the detection of class 1 is a false positive from the IoU threshold of 0.8
+    assert_close(first.ap50_95, 0.75);
+    assert_close(second.ap50_95, 0.3);
+    assert_close(evaluation.map50_95, 0.525);
+    assert_close(
+        evaluator.mean_average_precision(0.8, ApMethod::AllPoint),
+        0.375,
+    );
+    assert_close(
+        evaluator.mean_average_precision(0.5, ApMethod::Voc11Point),
+        (8.5 / 11.0 + 6.0 / 11.0) / 2.0,
+    );
+
+    let counts = first.counts;
+    assert_eq!(
+        (
+            counts.true_positives,
+            counts.false_positives,
+            counts.false_negatives
+        ),
+        (1, 2, 1)
+    );
+    let counts = evaluation.counts;
+    assert_eq!(
+        (
+            counts.true_positives,
+            counts.false_positives,
+            counts.false_negatives
+        ),
+        (2, 2, 2)
+    );
+    assert_close(counts.precision(), 0.5);
+    assert_close(counts.recall(), 0.5);
+    assert_close(counts.f1(), 0.5);
+    assert_close(counts.average_iou, (1.0 + 0.14 / 0.18) / 4.0);
+
+    // a low confidence threshold counts the detection of the second object
+    let counts = evaluator.counts(0.5, 0.1);
+    assert_eq!(
+        (counts[0].true_positives, counts[0].false_negatives),
+        (2, 0)
+    );
+}
+
+#[test]
+fn evaluate_without_detections() {
+    let mut evaluator = Evaluator::new();
+    evaluator.add_image(
+        &[GroundTruth::new(2, BoundingBox::new(0.5, 0.5, 0.2, 0.2))],
+        &[],
+    );
+    let evaluation = evaluator.evaluate(&EvalOptions::default());
+    assert_eq!(evaluation.classes.len(), 3);
+    assert_eq!((evaluation.map, evaluation.map50_95), (0.0, 0.0));
+    assert_eq!(evaluation.counts.false_negatives, 1);
+    assert_eq!(evaluation.counts.f1(), 0.0);
+}
@@ -0,0 +1,232 @@
+use darknet::{
+    export::{self, ImageDetections, COCO_CATEGORY_IDS},
+    BoundingBox, Error, Labels, OwnedDetection,
+};
+use darknet_sys as sys;
+use std::{
+    env,
+    ffi::{CStr, CString},
+    fs,
+    os::raw::{c_char, c_int, c_longlong, c_void},
+};
+
+extern "C" {
+    fn detection_to_json(
+        dets: *mut sys::detection,
+        nboxes: c_int,
+        classes: c_int,
+        names: *mut *mut c_char,
+        frame_id: c_longlong,
+        filename: *mut c_char,
+    ) -> *mut c_char;
+}
+
+fn labels() -> Labels {
+    Labels::new(vec![
+        "cat".to_owned(),
+        "dog".to_owned(),
+        "dont_show bird".to_owned(),
+    ])
+}
+
+fn image() -> ImageDetections {
+    ImageDetections::new(
+        "images/000000000139.jpg",
+        200,
+        100,
+        vec![
+            OwnedDetection::new(BoundingBox::new(0.5, 0.5, 0.25, 0.5), vec![0.75, 0.0, 0.0]),
+            // partially outside the image
+            OwnedDetection::new(BoundingBox::new(0.05, 0.9, 0.2, 0.4), vec![0.5, 0.25, 0.9]),
+            // no class above the threshold
+            OwnedDetection::new(BoundingBox::new(0.3, 0.3, 0.1, 0.1), vec![0.0, 0.0, 0.0]),
+        ],
+    )
+}
+
+/// Write with the writer function into a string.
+fn write_string<F>(write: F) -> Result<String, Error>
+where
+    F: FnOnce(&mut Vec<u8>) -> Result<(), Error>,
+{
+    let mut buffer = vec![];
+    write(&mut buffer)?;
+    Ok(String::from_utf8(buffer).unwrap())
+}
+
+#[test]
+fn image_ids() {
+    assert_eq!(image().id, 139);
+    assert_eq!(ImageDetections::new("dog.jpg", 1, 1, vec![]).id, 0);
+    assert_eq!(ImageDetections::new("12_b.png", 1, 1, vec![]).id, 12);
+}
+
+#[test]
+fn coco_json() -> Result<(), Error> {
+    let json = write_string(|buffer| export::write_coco_json(buffer, &[image()], None))?;
+    let expected = r#"[
+{"image_id":139, "category_id":0, "bbox":[75.000000, 25.000000, 50.000000, 50.000000], "score":0.750000},
+{"image_id":139, "category_id":0, "bbox":[0.000000, 70.000000, 30.000000, 30.000000], "score":0.500000},
+{"image_id":139, "category_id":1, "bbox":[0.000000, 70.000000, 30.000000, 30.000000], "score":0.250000},
+{"image_id":139, "category_id":2, "bbox":[0.000000, 70.000000, 30.000000, 30.000000], "score":0.900000}
+]
+"#;
+    assert_eq!(json, expected);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value.as_array().unwrap().len(), 4);
+
+    let json = write_string(|buffer| {
+        export::write_coco_json(buffer, vec![image()], Some(&COCO_CATEGORY_IDS[1..]))
+    })?;
+    assert!(json.contains(r#""category_id":2, "bbox":[75.0"#));
+    assert!(json.contains(r#""category_id":4, "bbox":[0.0"#));
+
+    let result = export::write_coco_json(vec![], &[image()], Some(&[1, 2]));
+    assert!(matches!(result, Err(Error::ConversionError { .. })));
+    Ok(())
+}
+
+#[test]
+fn match_darknet_json() -> Result<(), Error> {
+    let labels = labels();
+    let images = [image(), ImageDetections::new("empty.jpg", 10, 10, vec![])];
+    let json = write_string(|buffer| export::write_darknet_json(buffer, &images, &labels))?;
+
+    // libdarknet writes the objects of each image, joined into an array
+    let names: Vec<CString> = labels
+        .iter()
+        .map(|label| CString::new(label.as_str()).unwrap())
+        .collect();
+    let mut names: Vec<*mut c_char> = names.iter().map(|name| name.as_ptr() as *mut _).collect();
+    let mut expected = "[\n".to_owned();
+    for (index, image) in images.iter().enumerate() {
+        let mut probabilities: Vec<Vec<f32>> = image
+            .detections
+            .iter()
+            .map(|detection| detection.probabilities.clone())
+            .collect();
+        let mut dets: Vec<sys::detection> = image
+            .detections
+            .iter()
+            .zip(&mut probabilities)
+            .map(|(detection, probabilities)| {
+                let mut det: sys::detection = unsafe { std::mem::zeroed() };
+                det.bbox = detection.bbox;
+                det.classes = 3;
+                det.prob = probabilities.as_mut_ptr();
+                det
+            })
+            .collect();
+        let filename = CString::new(image.path.to_str().unwrap()).unwrap();
+        let object = unsafe {
+            let buffer = detection_to_json(
+                dets.as_mut_ptr(),
+                dets.len() as c_int,
+                3,
+                names.as_mut_ptr(),
+                index as c_longlong + 1,
+                filename.as_ptr() as *mut _,
+            );
+            let object = CStr::from_ptr(buffer).to_str().unwrap().to_owned();
+            libc::free(buffer as *mut c_void);
+            object
+        };
+        if index > 0 {
+            expected.push_str(", \n");
+        }
+        expected.push_str(&object);
+    }
+    expected.push_str("\n]");
+
+    assert_eq!(json, expected);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value[0]["objects"].as_array().unwrap().len(), 3);
+    assert_eq!(value[1]["objects"].as_array().unwrap().len(), 0);
+    Ok(())
+}
+
+#[test]
+fn voc_xml() -> Result<(), Error> {
+    let xml = write_string(|buffer| export::write_voc_xml(buffer, &image(), &labels()))?;
+    let expected = "<annotation>
+\t<folder>images</folder>
+\t<filename>000000000139.jpg</filename>
+\t<path>images/000000000139.jpg</path>
+\t<source>
+\t\t<database>Unknown</database>
+\t</source>
+\t<size>
+\t\t<width>200</width>
+\t\t<height>100</height>
+\t\t<depth>3</depth>
+\t</size>
+\t<segmented>0</segmented>
+\t<object>
+\t\t<name>cat</name>
+\t\t<pose>Unspecified</pose>
+\t\t<truncated>0</truncated>
+\t\t<difficult>0</difficult>
+\t\t<bndbox>
+\t\t\t<xmin>76</xmin>
+\t\t\t<ymin>26</ymin>
+\t\t\t<xmax>126</xmax>
+\t\t\t<ymax>76</ymax>
+\t\t</bndbox>
+\t</object>
+\t<object>
+\t\t<name>dont_show bird</name>
+\t\t<pose>Unspecified</pose>
+\t\t<truncated>0</truncated>
+\t\t<difficult>0</difficult>
+\t\t<bndbox>
+\t\t\t<xmin>1</xmin>
+\t\t\t<ymin>71</ymin>
+\t\t\t<xmax>31</xmax>
+\t\t\t<ymax>100</ymax>
+\t\t</bndbox>
+\t</object>
+</annotation>
+";
+    assert_eq!(xml, expected);
+
+    let mut escaped = image();
+    escaped.path = "a&b/<c>.jpg".into();
+    let xml = write_string(|buffer| {
+        export::write_voc_xml(buffer, &escaped, &Labels::new(vec!["\"q\"".to_owned()]))
+    })?;
+    assert!(xml.contains("<folder>a&amp;b</folder>"));
+    assert!(xml.contains("<filename>&lt;c&gt;.jpg</filename>"));
+    assert!(xml.contains("<name>&quot;q&quot;</name>"));
+    // classes without labels are named by their indexes
+    assert!(xml.contains("<name>2</name>"));
+    Ok(())
+}
+
+#[test]
+fn yolo_txt() -> Result<(), Error> {
+    let txt = write_string(|buffer| export::write_yolo_txt(buffer, &image()))?;
+    assert_eq!(
+        txt,
+        "0 0.5000 0.5000 0.2500 0.5000\n2 0.0500 0.9000 0.2000 0.4000\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn save_files() -> Result<(), Error> {
+    let dir = env::temp_dir().join("darknet-rust-export");
+    let images = vec![
+        image(),
+        ImageDetections::new("other/v1.2.png", 10, 10, vec![]),
+    ];
+
+    export::save_yolo_txt(&dir, &images)?;
+    export::save_voc_xml(&dir, &images, &labels())?;
+    let txt = fs::read_to_string(dir.join("000000000139.txt"))?;
+    assert_eq!(txt.lines().count(), 2);
+    assert_eq!(fs::read_to_string(dir.join("v1.2.txt"))?, "");
+    let xml = fs::read_to_string(dir.join("v1.2.xml"))?;
+    assert!(xml.contains("<filename>v1.2.png</filename>"));
+    assert!(dir.join("000000000139.xml").exists());
+    Ok(())
+}
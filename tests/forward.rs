@@ -0,0 +1,68 @@
+use darknet::{Error, LayerType, Network};
+
+const CFG: &str = r#"
+[net]
+width=2
+height=2
+channels=1
+
+[convolutional]
+filters=1
+size=1
+stride=1
+activation=linear
+
+[softmax]
+
+[cost]
+"#;
+
+fn weights_bytes(floats: &[f32]) -> Vec<u8> {
+    let mut bytes = vec![];
+    [0i32, 2, 0]
+        .iter()
+        .for_each(|word| bytes.extend(&word.to_le_bytes()));
+    bytes.extend(&0u64.to_le_bytes());
+    floats
+        .iter()
+        .for_each(|value| bytes.extend(&value.to_le_bytes()));
+    bytes
+}
+
+#[test]
+fn forward_raw_input() -> Result<(), Error> {
+    // y = 2x + 0.5, then softmax over the 4 positions
+    let weights = weights_bytes(&[0.5, 2.0]);
+    let mut net = Network::from_bytes(CFG.as_bytes(), Some(&weights), false)?;
+
+    let output_layer = net.output_layer();
+    assert_eq!(output_layer.type_(), Some(LayerType::Softmax));
+    assert_eq!(output_layer.num_outputs(), 4);
+
+    let output = net.forward(&[0.0, 0.0, 0.0, 0.5_f32.ln()])?.to_vec();
+    let expected = [1.0, 1.0, 1.0, 0.25].map(|value| value / 3.25);
+    for (value, expected) in output.iter().zip(&expected) {
+        assert!((value - expected).abs() < 1e-6, "{:?}", output);
+    }
+    Ok(())
+}
+
+#[test]
+fn forward_wrong_input_size() -> Result<(), Error> {
+    let mut net = Network::from_cfg_str(CFG, None::<&str>, false)?;
+    match net.forward(&[0.0; 3]) {
+        Err(Error::InputSizeError { expected, found }) => assert_eq!((expected, found), (4, 3)),
+        other => panic!("unexpected result {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn forward_with_batch_config() -> Result<(), Error> {
+    let cfg = CFG.replace("[net]\n", "[net]\nbatch=4\n");
+    let weights = weights_bytes(&[0.5, 2.0]);
+    let mut net = Network::from_bytes(cfg.as_bytes(), Some(&weights), false)?;
+    let output = net.forward(&[0.0; 4])?;
+    assert_eq!(output, [0.25; 4]);
+    Ok(())
+}
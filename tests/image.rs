@@ -0,0 +1,140 @@
+//! The tests other than `darknet_interop` only run Rust code, so they can check the aliasing of
+//! the pixel data with `cargo miri test --test image`.
+
+use darknet::Image;
+use darknet_sys as sys;
+use image::{Rgb, RgbImage};
+
+/// A 2 channel 3x2 image where each value encodes its (c, y, x).
+fn image() -> Image {
+    let mut image = Image::zeros(3, 2, 2);
+    for c in 0..2 {
+        for y in 0..2 {
+            for x in 0..3 {
+                image[(c, y, x)] = (c * 100 + y * 10 + x) as f32;
+            }
+        }
+    }
+    image
+}
+
+#[test]
+fn pixels() {
+    let mut image = Image::zeros(3, 2, 2);
+    assert_eq!(image.shape(), (2, 2, 3));
+    assert!(image.get_data().iter().all(|&value| value == 0.0));
+
+    image.set_pixel(1, 1, 2, 0.5);
+    *image.get_pixel_mut(0, 1, 0).unwrap() = 0.25;
+    assert_eq!(image[(1, 1, 2)], 0.5);
+    assert_eq!(image.get_pixel(0, 1, 0), Some(0.25));
+    // (c * height + y) * width + x
+    assert_eq!(image.get_data()[(2 + 1) * 3 + 2], 0.5);
+    assert_eq!(image.get_pixel(2, 0, 0), None);
+    assert_eq!(image.get_pixel(0, 2, 0), None);
+    assert!(image.get_pixel_mut(0, 0, 3).is_none());
+
+    let data = image.get_data_mut();
+    data[0] = 1.0;
+    assert_eq!(image[(0, 0, 0)], 1.0);
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn pixel_out_of_range() {
+    let mut image = Image::zeros(3, 2, 2);
+    image[(0, 0, 3)] = 1.0;
+}
+
+#[test]
+fn rows_and_planes() {
+    let mut image = image();
+    assert_eq!(image.row(1, 0), [100.0, 101.0, 102.0]);
+    assert_eq!(image.plane(0), [0.0, 1.0, 2.0, 10.0, 11.0, 12.0]);
+    let planes: Vec<_> = image.planes().map(|plane| plane[4]).collect();
+    assert_eq!(planes, [11.0, 111.0]);
+
+    image
+        .row_mut(0, 1)
+        .iter_mut()
+        .for_each(|value| *value = -1.0);
+    image.plane_mut(1)[0] = -2.0;
+    for plane in image.planes_mut() {
+        plane[1] += 0.5;
+    }
+    assert_eq!(image.plane(0), [0.0, 1.5, 2.0, -1.0, -1.0, -1.0]);
+    assert_eq!(image.row(1, 0), [-2.0, 101.5, 102.0]);
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn row_out_of_range() {
+    image().row(0, 2);
+}
+
+#[test]
+fn clone_copies_data() {
+    let image = image();
+    let mut cloned = image.clone();
+    assert_eq!(cloned.get_data(), image.get_data());
+    assert_ne!(cloned.get_raw_data(), image.get_raw_data());
+
+    cloned[(0, 0, 0)] = -1.0;
+    assert_eq!(image[(0, 0, 0)], 0.0);
+}
+
+#[test]
+fn empty_image() {
+    let mut image = Image::zeros(0, 4, 3);
+    assert_eq!(image.shape(), (3, 4, 0));
+    assert!(image.get_data().is_empty());
+    assert!(image.get_data_mut().is_empty());
+    assert_eq!(image.get_pixel(0, 0, 0), None);
+    assert_eq!(image.clone().shape(), (3, 4, 0));
+}
+
+#[test]
+fn raw_round_trip() {
+    let image = image();
+    let data = image.get_raw_data();
+    let raw = image.into_raw();
+    assert_eq!((raw.w, raw.h, raw.c), (3, 2, 2));
+    assert_eq!(raw.data, data);
+
+    let mut image = unsafe { Image::from_raw(raw) };
+    assert_eq!(image.as_raw().data, data);
+    assert_eq!(image[(1, 1, 2)], 112.0);
+    unsafe { image.as_raw_mut().c = 1 };
+    assert_eq!(image.get_data().len(), 6);
+}
+
+#[test]
+fn image_buffer_round_trip() {
+    let buffer = RgbImage::from_fn(4, 3, |x, y| Rgb([(x * 60) as u8, (y * 100) as u8, 255]));
+    let image = Image::from(&buffer);
+    assert_eq!(image.shape(), (3, 3, 4));
+    assert_eq!(image[(0, 2, 3)], 180.0 / 255.0);
+    assert_eq!(image[(1, 2, 3)], 200.0 / 255.0);
+    assert_eq!(image.row(2, 1), [1.0; 4]);
+    assert_eq!(image.to_image_buffer::<Rgb<u8>>().unwrap(), buffer);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn darknet_interop() {
+    // images allocated by Rust are freed by libdarknet, and the other way around
+    let image = image();
+    unsafe { sys::free_image(image.into_raw()) };
+
+    let resized = image_buffer().resize(8, 6);
+    assert_eq!(resized.shape(), (3, 6, 8));
+    let cropped = resized.crop(-2, 0, 4, 2);
+    assert_eq!(cropped[(0, 0, 0)], resized[(0, 0, 0)]);
+    assert_eq!(cropped[(1, 1, 3)], resized[(1, 1, 1)]);
+}
+
+fn image_buffer() -> Image {
+    Image::from(RgbImage::from_fn(4, 3, |x, y| {
+        Rgb([(x * 60) as u8, (y * 100) as u8, 255])
+    }))
+}
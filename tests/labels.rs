@@ -0,0 +1,91 @@
+mod common;
+
+use common::write_temp;
+use darknet::{BoundingBox, Error, Labels, Network, OwnedDetection};
+
+const CFG: &str = r#"
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=21
+size=3
+stride=4
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=4,4, 8,8, 16,16
+classes=2
+num=3
+"#;
+
+#[test]
+fn open_names_file() -> Result<(), Error> {
+    let path = write_temp("labels-open.names", "person\nbicycle\r\ntraffic light\n");
+    let labels = Labels::open(&path)?;
+    assert_eq!(labels.len(), 3);
+    assert_eq!(labels.get(2), Some("traffic light"));
+    assert_eq!(&labels[1], "bicycle");
+    assert_eq!(labels.get(3), None);
+    assert_eq!(labels.position("bicycle"), Some(1));
+
+    let result = Labels::open("./no-such-dir/coco.names");
+    assert!(matches!(result, Err(Error::LabelsOpenError { .. })));
+    Ok(())
+}
+
+#[test]
+fn open_data_file() -> Result<(), Error> {
+    let names = write_temp("labels-data.names", "cat\ndog\n");
+    let data = write_temp(
+        "labels-coco.data",
+        format!(
+            "# comment\nclasses = 2\ntrain = train.txt\n names = {}\nnames = ignored.names\n",
+            names.display()
+        ),
+    );
+    let labels = Labels::from_data_file(&data)?;
+    assert_eq!(labels.as_slice(), ["cat", "dog"]);
+
+    let data = write_temp(
+        "labels-no-names.data",
+        "classes=2\n;names=commented.names\n",
+    );
+    let result = Labels::from_data_file(&data);
+    assert!(matches!(result, Err(Error::InvalidDataFileError { .. })));
+    Ok(())
+}
+
+#[test]
+fn check_against_network() -> Result<(), Error> {
+    let net = Network::from_cfg_str(CFG, None::<&str>, false)?;
+    assert_eq!(net.num_classes(), 2);
+    let labels: Labels = vec!["cat".to_owned(), "dog".to_owned()].into();
+    labels.check(&net)?;
+
+    let labels = Labels::new(vec!["cat".to_owned()]);
+    match labels.check(&net) {
+        Err(Error::LabelCountError { labels, classes }) => assert_eq!((labels, classes), (1, 2)),
+        other => panic!("unexpected result {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn best_class_label() {
+    let detection = OwnedDetection::new(BoundingBox::new(0.5, 0.5, 0.1, 0.1), vec![0.2, 0.7, 0.8]);
+    let labels = Labels::new(vec!["cat".to_owned(), "dog".to_owned(), "bird".to_owned()]);
+    assert_eq!(
+        detection.best_class_label(&labels, None),
+        Some(("bird", 0.8))
+    );
+    assert_eq!(detection.best_class_label(&labels, Some(0.9)), None);
+
+    // a class without a label has no best label
+    let labels = Labels::new(vec!["cat".to_owned(), "dog".to_owned()]);
+    assert_eq!(detection.best_class_label(&labels, None), None);
+}
@@ -0,0 +1,173 @@
+use darknet::{BBox, BoundingBox, Image, LetterBox};
+use darknet_sys as sys;
+use image::{DynamicImage, RgbImage};
+use std::{mem, os::raw::c_int};
+
+extern "C" {
+    // exported by libdarknet, and used by get_network_boxes for [yolo] layers
+    fn correct_yolo_boxes(
+        dets: *mut sys::detection,
+        n: c_int,
+        w: c_int,
+        h: c_int,
+        netw: c_int,
+        neth: c_int,
+        relative: c_int,
+        letter: c_int,
+    );
+}
+
+const SIZES: [(usize, usize); 4] = [(640, 480), (333, 1000), (416, 416), (1279, 721)];
+
+fn boxes() -> Vec<BoundingBox> {
+    vec![
+        BoundingBox::new(0.5, 0.5, 0.25, 0.125),
+        BoundingBox::new(0.13, 0.87, 0.33, 0.71),
+        BoundingBox::new(0.9, 0.02, 0.07, 0.01),
+    ]
+}
+
+fn c_correction(
+    boxes: &[BoundingBox],
+    (width, height): (usize, usize),
+    relative: bool,
+    letter: bool,
+) -> Vec<BoundingBox> {
+    let mut dets: Vec<sys::detection> = boxes
+        .iter()
+        .map(|&bbox| {
+            let mut det: sys::detection = unsafe { mem::zeroed() };
+            det.bbox = bbox.into();
+            det
+        })
+        .collect();
+    unsafe {
+        correct_yolo_boxes(
+            dets.as_mut_ptr(),
+            dets.len() as c_int,
+            width as c_int,
+            height as c_int,
+            416,
+            416,
+            relative as c_int,
+            letter as c_int,
+        );
+    }
+    dets.iter().map(|det| det.bbox.into()).collect()
+}
+
+/// libdarknet is built with -Ofast, so the values may differ in the last bits.
+fn assert_boxes_close(actual: &[BoundingBox], expected: &[BoundingBox]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, b) in actual.iter().zip(expected) {
+        let close = a
+            .to_cxcywh()
+            .iter()
+            .zip(&b.to_cxcywh())
+            .all(|(a, b)| (a - b).abs() <= b.abs().max(1.0) * 1e-6);
+        assert!(close, "expected {:?}, found {:?}", b, a);
+    }
+}
+
+#[test]
+fn match_c_correction() {
+    for &size in &SIZES {
+        let transform = LetterBox::new(size.0, size.1, 416, 416);
+        let normalized: Vec<_> = boxes().iter().map(|b| transform.to_image(b)).collect();
+        assert_boxes_close(&normalized, &c_correction(&boxes(), size, true, true));
+        let pixels: Vec<_> = boxes()
+            .iter()
+            .map(|b| transform.to_image_pixels(b))
+            .collect();
+        assert_boxes_close(&pixels, &c_correction(&boxes(), size, false, true));
+
+        let transform = LetterBox::stretch(size.0, size.1, 416, 416);
+        let stretched: Vec<_> = boxes().iter().map(|b| transform.to_image(b)).collect();
+        assert_boxes_close(&stretched, &c_correction(&boxes(), size, true, false));
+    }
+}
+
+#[test]
+fn map_to_network_and_back() {
+    let transform = LetterBox::new(640, 480, 416, 416);
+    assert_eq!(
+        (transform.resized_width, transform.resized_height),
+        (416, 312)
+    );
+    assert_eq!((transform.pad_x(), transform.pad_y()), (0.0, 52.0));
+    assert_eq!(transform.scale_x(), 0.65);
+    assert_eq!(transform.scale_y(), 0.65);
+
+    // the image corners are the corners of the resized image
+    let image = BoundingBox::from_xyxy(0.0, 0.0, 640.0, 480.0);
+    let network = transform.from_image_pixels(&image).to_pixels(416, 416);
+    assert_eq!(network.to_xyxy(), [0.0, 52.0, 416.0, 364.0]);
+
+    for bbox in boxes() {
+        let mapped = transform.to_image(&transform.to_network(&bbox));
+        let diffs = [
+            mapped.x - bbox.x,
+            mapped.y - bbox.y,
+            mapped.w - bbox.w,
+            mapped.h - bbox.h,
+        ];
+        assert!(diffs.iter().all(|diff| diff.abs() < 1e-6), "{:?}", diffs);
+    }
+}
+
+#[test]
+fn letter_box_image_with_transform() {
+    let image = Image::from(DynamicImage::ImageRgb8(RgbImage::new(100, 50)));
+    let (boxed, transform) = image.letter_box_with_transform(64, 64);
+    assert_eq!((boxed.width(), boxed.height()), (64, 64));
+    assert_eq!(transform, LetterBox::new(100, 50, 64, 64));
+    assert_eq!(
+        (transform.resized_width, transform.resized_height),
+        (64, 32)
+    );
+
+    let bbox: BBox = transform
+        .to_network(&BoundingBox::new(0.5, 0.5, 1.0, 1.0))
+        .into();
+    assert_eq!((bbox.x, bbox.y, bbox.w, bbox.h), (0.5, 0.5, 1.0, 0.5));
+}
+
+#[test]
+fn empty_image() {
+    for &(width, height) in &[(0, 0), (0, 50), (100, 0)] {
+        let image = Image::zeros(width, height, 3);
+        let (boxed, transform) = image.letter_box_with_transform(64, 48);
+        assert_eq!(boxed.shape(), (3, 48, 64));
+        assert!(boxed.get_data().iter().all(|&value| value == 0.5));
+
+        assert!(transform.is_empty());
+        assert_eq!((transform.resized_width, transform.resized_height), (0, 0));
+        assert_eq!((transform.scale_x(), transform.scale_y()), (0.0, 0.0));
+        assert_eq!((transform.pad_x(), transform.pad_y()), (32.0, 24.0));
+        let bbox = BoundingBox::new(0.5, 0.5, 0.25, 0.25);
+        assert_eq!(transform.to_image(&bbox), BoundingBox::default());
+        assert_eq!(transform.to_network(&bbox), BoundingBox::default());
+        assert_eq!(transform.to_image_pixels(&bbox), BoundingBox::default());
+        assert_eq!(transform.from_image_pixels(&bbox), BoundingBox::default());
+
+        let stretched = LetterBox::stretch(width, height, 64, 48);
+        assert_eq!((stretched.scale_x(), stretched.scale_y()), (0.0, 0.0));
+        assert_eq!(stretched.to_image(&bbox), BoundingBox::default());
+    }
+}
+
+#[test]
+fn resized_image_larger_than_network() {
+    // a hand-built transform that crops the center of the image scaled by 2
+    let transform = LetterBox {
+        resized_width: 128,
+        resized_height: 96,
+        ..LetterBox::new(64, 48, 64, 48)
+    };
+    assert_eq!((transform.pad_x(), transform.pad_y()), (-32.0, -24.0));
+
+    let bbox = BoundingBox::new(0.5, 0.5, 0.25, 0.25);
+    let network = transform.to_network(&bbox);
+    assert_eq!(network, BoundingBox::new(0.5, 0.5, 0.5, 0.5));
+    assert_eq!(transform.to_image(&network), bbox);
+}
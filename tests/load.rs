@@ -0,0 +1,110 @@
+mod common;
+
+use common::write_temp;
+use darknet::{cfg::Config, Error, Network};
+
+const CFG: &str = "[net]\nwidth=32\nheight=32\nchannels=3\n";
+
+#[test]
+fn missing_cfg() {
+    let result = Network::load("./no-such-model.cfg", None::<&str>, false);
+    assert!(matches!(result, Err(Error::ConfigOpenError { .. })));
+}
+
+#[test]
+fn cfg_without_sections() {
+    let cfg = write_temp("load-empty.cfg", b"# comment only\n\n; another\n");
+    let result = Network::load(&cfg, None::<&str>, false);
+    assert!(matches!(result, Err(Error::EmptyConfigError)));
+}
+
+#[test]
+fn cfg_without_net_section() {
+    let cfg = write_temp("load-no-net.cfg", b"[convolutional]\nfilters = 16\n");
+    match Network::load(&cfg, None::<&str>, false) {
+        Err(Error::InvalidNetSectionError { found }) => assert_eq!(found, "[convolutional]"),
+        other => panic!("unexpected result {:?}", other.err()),
+    }
+}
+
+#[test]
+fn missing_weights() {
+    let cfg = write_temp("load-missing-weights.cfg", CFG.as_bytes());
+    let result = Network::load(&cfg, Some("./no-such-model.weights"), false);
+    assert!(matches!(result, Err(Error::WeightsOpenError { .. })));
+}
+
+#[test]
+fn invalid_weights() {
+    let cfg = write_temp("load-invalid-weights.cfg", CFG.as_bytes());
+
+    // version 0.2.0 requires a 64-bit seen counter
+    let mut header = vec![];
+    [0i32, 2, 0]
+        .iter()
+        .for_each(|v| header.extend(&v.to_le_bytes()));
+    header.extend(&7u32.to_le_bytes());
+    let weights = write_temp("load-truncated.weights", &header);
+    let result = Network::load(&cfg, Some(&weights), false);
+    assert!(matches!(result, Err(Error::InvalidWeightsError { .. })));
+
+    header.extend(&[0u8; 4 + 3]);
+    let weights = write_temp("load-partial.weights", &header);
+    let result = Network::load(&cfg, Some(&weights), false);
+    assert!(matches!(result, Err(Error::InvalidWeightsError { .. })));
+}
+
+#[test]
+fn cfg_with_loose_options() -> Result<(), Error> {
+    // libdarknet skips the malformed line that the config parser rejects
+    let cfg = format!(
+        "{}\n[convolutional]\nfilters=8\nsize=3\nstride=1\npad=1\nactivation=leaky\nloose\n",
+        CFG
+    );
+    assert!(cfg.parse::<Config>().is_err());
+    let net = Network::from_cfg_str(&cfg, None::<&str>, false)?;
+    assert_eq!(net.num_layers(), 1);
+    Ok(())
+}
+
+#[test]
+fn load_from_cfg_str() -> Result<(), Error> {
+    let cfg = format!(
+        "{}\n[convolutional]\nfilters=8\nsize=3\nstride=1\npad=1\nactivation=leaky\n",
+        CFG
+    );
+    let net = Network::from_cfg_str(&cfg, None::<&str>, false)?;
+    assert_eq!(net.input_shape(), (3, 32, 32));
+    assert_eq!(net.num_layers(), 1);
+    assert_eq!(net.get_layer(0).unwrap().output_shape(), (32, 32, 8));
+
+    let result = Network::from_cfg_str("[yolo]\n", None::<&str>, false);
+    assert!(matches!(result, Err(Error::InvalidNetSectionError { .. })));
+    Ok(())
+}
+
+#[test]
+fn load_from_bytes() -> Result<(), Error> {
+    let cfg = format!(
+        "{}\n[convolutional]\nfilters=8\nsize=3\nstride=1\npad=1\nactivation=leaky\n",
+        CFG
+    );
+
+    // header, then 8 biases and 8x3x3x3 kernel weights
+    let mut weights = vec![];
+    [0i32, 2, 0]
+        .iter()
+        .for_each(|v| weights.extend(&v.to_le_bytes()));
+    weights.extend(&64u64.to_le_bytes());
+    (0..8 + 8 * 3 * 3 * 3).for_each(|_| weights.extend(&0.5f32.to_le_bytes()));
+
+    let net = Network::from_bytes(cfg.as_bytes(), Some(&weights), false)?;
+    assert_eq!(net.input_shape(), (3, 32, 32));
+
+    let net = Network::from_reader(cfg.as_bytes(), Some(&weights[..]), false)?;
+    assert_eq!(net.num_layers(), 1);
+
+    let result = Network::from_bytes(cfg.as_bytes(), Some(&weights[..10]), false);
+    assert!(matches!(result, Err(Error::InvalidWeightsError { .. })));
+    Ok(())
+}
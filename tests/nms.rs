@@ -0,0 +1,214 @@
+use darknet::{
+    nms::{self, NmsOptions, Suppression},
+    BBox, BoundingBox, NmsKind, OwnedDetection,
+};
+use darknet_sys as sys;
+use std::os::raw::c_int;
+
+/// Deterministic pseudo-random detections with 3 classes.
+fn random_detections(count: usize) -> Vec<OwnedDetection> {
+    let mut state = 12345u32;
+    let mut next = move || {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (state >> 8) as f32 / (1 << 24) as f32
+    };
+    (0..count)
+        .map(|_| {
+            let bbox = BoundingBox::new(
+                0.3 + next() * 0.4,
+                0.3 + next() * 0.4,
+                0.1 + next() * 0.3,
+                0.1 + next() * 0.3,
+            );
+            let class = (next() * 3.0) as usize;
+            let mut probabilities = vec![0.0; 3];
+            probabilities[class] = 0.05 + next() * 0.95;
+            OwnedDetection::new(bbox, probabilities)
+        })
+        .collect()
+}
+
+/// Run the NMS of libdarknet and return the probabilities in the input order.
+fn darknet_nms(detections: &[OwnedDetection], kind: NmsKind, beta: f32) -> Vec<Vec<f32>> {
+    let mut probabilities: Vec<Vec<f32>> = detections
+        .iter()
+        .map(|detection| detection.probabilities.clone())
+        .collect();
+    let mut dets: Vec<sys::detection> = detections
+        .iter()
+        .zip(&mut probabilities)
+        .enumerate()
+        .map(|(index, (detection, probabilities))| {
+            let mut det: sys::detection = unsafe { std::mem::zeroed() };
+            det.bbox = detection.bbox;
+            det.classes = 3;
+            det.prob = probabilities.as_mut_ptr();
+            det.objectness = detection.objectness;
+            // remember the input order, since libdarknet sorts the detections
+            det.points = index as c_int;
+            det
+        })
+        .collect();
+    unsafe {
+        if kind == NmsKind::Default {
+            sys::do_nms_sort(dets.as_mut_ptr(), dets.len() as c_int, 3, 0.45);
+        } else {
+            sys::diounms_sort(
+                dets.as_mut_ptr(),
+                dets.len() as c_int,
+                3,
+                0.45,
+                kind as sys::NMS_KIND,
+                beta,
+            );
+        }
+    }
+    probabilities
+}
+
+fn sorted_probabilities(mut detections: Vec<OwnedDetection>) -> Vec<Vec<f32>> {
+    let mut probabilities: Vec<_> = detections
+        .drain(..)
+        .map(|detection| detection.probabilities)
+        .collect();
+    probabilities.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    probabilities
+}
+
+#[test]
+fn match_darknet_nms() {
+    let detections = random_detections(60);
+    for &(kind, beta) in &[
+        (NmsKind::Default, 0.0),
+        (NmsKind::Greedy, 0.0),
+        (NmsKind::Diou, 0.8),
+    ] {
+        let mut expected = darknet_nms(&detections, kind, beta);
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let mut suppressed = detections.clone();
+        let options = NmsOptions {
+            suppression: Suppression::from_nms_kind(kind, beta),
+            ..NmsOptions::default()
+        };
+        nms::nms(&mut suppressed, &options);
+        let kept = suppressed
+            .iter()
+            .filter(|detection| detection.best_class(Some(0.0)).is_some())
+            .count();
+        assert!(kept > 3 && kept < detections.len(), "{}", kept);
+        assert_eq!(sorted_probabilities(suppressed), expected, "{:?}", kind);
+    }
+}
+
+#[test]
+fn class_agnostic_nms() {
+    let a = OwnedDetection::new(BoundingBox::new(0.5, 0.5, 0.2, 0.2), vec![0.9, 0.0]);
+    let b = OwnedDetection::new(BoundingBox::new(0.51, 0.5, 0.2, 0.2), vec![0.0, 0.8]);
+    let c = OwnedDetection::new(BoundingBox::new(0.1, 0.1, 0.1, 0.1), vec![0.0, 0.3]);
+
+    let mut per_class = vec![b.clone(), a.clone(), c.clone()];
+    nms::nms(&mut per_class, &NmsOptions::default());
+    let scores: Vec<_> = per_class
+        .iter()
+        .map(|detection| detection.best_class(None).unwrap())
+        .collect();
+    assert_eq!(scores, [(0, 0.9), (1, 0.8), (1, 0.3)]);
+
+    let mut agnostic = vec![b, a, c];
+    nms::nms(
+        &mut agnostic,
+        &NmsOptions {
+            class_agnostic: true,
+            ..NmsOptions::default()
+        },
+    );
+    let probabilities: Vec<_> = agnostic
+        .iter()
+        .map(|detection| detection.probabilities.clone())
+        .collect();
+    assert_eq!(probabilities, [[0.9, 0.0], [0.0, 0.3], [0.0, 0.0]]);
+}
+
+#[test]
+fn soft_nms() {
+    // the boxes overlap with an IoU of 0.6
+    let a = BoundingBox::from_xyxy(0.0, 0.0, 0.4, 0.1);
+    let b = BoundingBox::from_xyxy(0.1, 0.0, 0.5, 0.1);
+    assert!((a.iou(&b) - 0.6).abs() < 1e-6);
+    let detections = vec![
+        OwnedDetection::new(b, vec![0.8]),
+        OwnedDetection::new(a, vec![0.9]),
+    ];
+
+    let mut linear = detections.clone();
+    nms::nms(
+        &mut linear,
+        &NmsOptions {
+            suppression: Suppression::SoftLinear,
+            iou_threshold: 0.5,
+            ..NmsOptions::default()
+        },
+    );
+    assert_eq!(linear[0].probabilities, [0.9]);
+    assert!((linear[1].probabilities[0] - 0.8 * 0.4).abs() < 1e-6);
+
+    let mut gaussian = detections.clone();
+    nms::nms(
+        &mut gaussian,
+        &NmsOptions {
+            suppression: Suppression::SoftGaussian { sigma: 0.5 },
+            ..NmsOptions::default()
+        },
+    );
+    assert!((gaussian[1].probabilities[0] - 0.8 * (-0.36f32 / 0.5).exp()).abs() < 1e-6);
+
+    // decayed scores below the threshold are removed
+    let mut thresholded = detections;
+    nms::nms(
+        &mut thresholded,
+        &NmsOptions {
+            suppression: Suppression::SoftLinear,
+            iou_threshold: 0.5,
+            class_agnostic: false,
+            score_threshold: 0.5,
+        },
+    );
+    assert_eq!(thresholded[1].probabilities, [0.0]);
+}
+
+#[test]
+fn fuse_boxes_of_models() {
+    let first = vec![
+        OwnedDetection::new(BoundingBox::from_xyxy(0.1, 0.1, 0.3, 0.3), vec![0.8, 0.0]),
+        OwnedDetection::new(BoundingBox::from_xyxy(0.6, 0.6, 0.9, 0.9), vec![0.0, 0.5]),
+    ];
+    let second = vec![OwnedDetection::new(
+        BoundingBox::from_xyxy(0.2, 0.1, 0.4, 0.3),
+        vec![0.4, 0.0],
+    )];
+
+    let fused = nms::weighted_box_fusion(&[first.clone(), second.clone()], None, 0.3, 0.0);
+    assert_eq!(fused.len(), 2);
+    // the first cluster averages the corners weighted by 0.8 and 0.4
+    let [left, top, right, bottom] = fused[0].bounding_box().to_xyxy();
+    let expected = [
+        (0.1 * 0.8 + 0.2 * 0.4) / 1.2,
+        0.1,
+        (0.3 * 0.8 + 0.4 * 0.4) / 1.2,
+        0.3,
+    ];
+    for (value, expected) in [left, top, right, bottom].iter().zip(&expected) {
+        assert!((value - expected).abs() < 1e-6, "{} != {}", value, expected);
+    }
+    assert!((fused[0].probabilities[0] - 0.6).abs() < 1e-6);
+    assert_eq!(fused[0].sort_class, 0);
+    // a box found by one of two models gets half its score
+    assert_eq!(fused[1].probabilities, [0.0, 0.25]);
+
+    // the weights scale the scores of each model
+    let fused = nms::weighted_box_fusion(&[first, second], Some(&[1.0, 3.0]), 0.3, 0.0);
+    assert!((fused[0].probabilities[0] - (0.8 + 1.2) / 2.0 * 2.0 / 4.0).abs() < 1e-6);
+    let bbox = BBox::from(BoundingBox::from_xyxy(0.6, 0.6, 0.9, 0.9));
+    assert!((fused[1].bbox.x - bbox.x).abs() < 1e-6);
+}
@@ -0,0 +1,232 @@
+use darknet::{
+    nms::NmsOptions, BoundingBox, Detections, Error, Image, Network, NmsKind, OwnedDetection,
+    PredictOptions, TileOptions,
+};
+use image::{Rgb, RgbImage};
+
+const CFG: &str = r#"
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=21
+size=3
+stride=4
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=4,4, 8,8, 16,16
+classes=2
+num=3
+"#;
+
+fn image() -> RgbImage {
+    RgbImage::from_fn(48, 32, |x, y| Rgb([(x * 5) as u8, (y * 7) as u8, 128]))
+}
+
+fn best_scores(detections: &Detections, classes: &[usize]) -> Vec<f32> {
+    detections
+        .iter()
+        .map(|detection| {
+            classes
+                .iter()
+                .map(|&class| detection.probabilities()[class])
+                .fold(0.0, f32::max)
+        })
+        .collect()
+}
+
+#[test]
+fn default_options() {
+    let options = PredictOptions::new();
+    assert_eq!(
+        (options.thresh, options.hier_thresh, options.nms),
+        (0.25, 0.5, 0.45)
+    );
+    assert!(!options.letter_box && options.relative);
+    assert_eq!(
+        (options.nms_kind, options.classes, options.max_detections),
+        (None, None, None)
+    );
+
+    let options = PredictOptions::new()
+        .thresh(0.1)
+        .nms_kind(NmsKind::Diou)
+        .classes(vec![1])
+        .max_detections(3);
+    assert_eq!(options.thresh, 0.1);
+    assert_eq!(options.nms_kind, Some(NmsKind::Diou));
+    assert_eq!(options.classes, Some(vec![1]));
+    assert_eq!(options.max_detections, Some(3));
+}
+
+#[test]
+fn predict_with_options() -> Result<(), Error> {
+    let mut net = Network::from_cfg_str(CFG, None::<&str>, false)?;
+    let options = PredictOptions::new().thresh(0.05).letter_box(true);
+
+    let positional = net.predict(image(), 0.05, 0.5, 0.45, true);
+    let named = net.predict_with(image(), &options);
+    assert!(!named.is_empty());
+    assert_eq!(named.len(), positional.len());
+    for (a, b) in named.iter().zip(positional.iter()) {
+        assert_eq!(a.probabilities(), b.probabilities());
+    }
+
+    // boxes in pixels are the normalized boxes scaled by the image size
+    let pixels = net.predict_with(image(), &options.clone().relative(false));
+    for (a, b) in pixels.iter().zip(named.iter()) {
+        let (a, b) = (a.bounding_box(), b.bounding_box().to_pixels(48, 32));
+        let diffs = [a.x - b.x, a.y - b.y, a.w - b.w, a.h - b.h];
+        assert!(diffs.iter().all(|diff| diff.abs() < 1e-3), "{:?}", diffs);
+    }
+    Ok(())
+}
+
+#[test]
+fn filter_classes_and_count() -> Result<(), Error> {
+    let mut net = Network::from_cfg_str(CFG, None::<&str>, false)?;
+    let options = PredictOptions::new().thresh(0.05).nms(0.0);
+    let all = net.predict_with(image(), &options);
+    let expected = best_scores(&all, &[1])
+        .into_iter()
+        .filter(|&score| score > 0.0)
+        .count();
+    assert!(expected > 2);
+
+    let filtered = net.predict_with(image(), &options.clone().classes(vec![1]));
+    assert_eq!(filtered.len(), expected);
+    let scores = best_scores(&filtered, &[1]);
+    assert!(scores.iter().all(|&score| score >= 0.05));
+    assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+
+    let limited = net.predict_with(image(), &options.clone().classes(vec![1]).max_detections(2));
+    assert_eq!(limited.len(), 2);
+    assert_eq!(best_scores(&limited, &[1]), scores[..2]);
+
+    let limited = net.predict_with(image(), &options.max_detections(1));
+    assert_eq!(limited.len(), 1);
+    Ok(())
+}
+
+fn large_image() -> RgbImage {
+    RgbImage::from_fn(96, 64, |x, y| {
+        Rgb([
+            (x * 11 % 256) as u8,
+            (y * 7 % 256) as u8,
+            ((x + y) * 3) as u8,
+        ])
+    })
+}
+
+fn score(detection: &OwnedDetection) -> f32 {
+    detection.best_class(None).map_or(0.0, |(_, prob)| prob)
+}
+
+#[test]
+fn default_tile_options() {
+    let options = TileOptions::new();
+    assert_eq!((options.tile_width, options.tile_height), (None, None));
+    assert_eq!((options.overlap, options.full_image), (0.2, true));
+    assert_eq!(options.predict, PredictOptions::default());
+    assert_eq!(options.nms, Some(NmsOptions::default()));
+
+    let options = TileOptions::new()
+        .tile_size(64, 48)
+        .overlap(0.5)
+        .full_image(false)
+        .nms(None);
+    assert_eq!(
+        (options.tile_width, options.tile_height),
+        (Some(64), Some(48))
+    );
+    assert_eq!(
+        (options.overlap, options.full_image, options.nms),
+        (0.5, false, None)
+    );
+}
+
+#[test]
+fn predict_tiled_matches_tiles() -> Result<(), Error> {
+    let mut net = Network::from_cfg_str(CFG, None::<&str>, false)?;
+    let predict = PredictOptions::new().thresh(0.05);
+    let options = TileOptions::new()
+        .tile_size(32, 32)
+        .overlap(0.0)
+        .full_image(false)
+        .nms(None)
+        .predict(predict.clone());
+    let tiled = net.predict_tiled(large_image(), &options);
+
+    // predict the 3x2 tiles one by one
+    let image = Image::from(&large_image());
+    let mut expected = vec![];
+    for top in [0, 32] {
+        for left in [0, 32, 64] {
+            let tile = image.crop(left, top, 32, 32);
+            for detection in net.predict_with(&tile, &predict).iter() {
+                let mut detection = OwnedDetection::from(detection);
+                let bbox = detection.bounding_box().to_pixels(32, 32);
+                let bbox =
+                    BoundingBox::new(bbox.x + left as f32, bbox.y + top as f32, bbox.w, bbox.h);
+                detection.bbox = bbox.to_normalized(96, 64).into();
+                expected.push(detection);
+            }
+        }
+    }
+    expected.retain(|detection| score(detection) > 0.0);
+    expected.sort_by(|a, b| score(b).partial_cmp(&score(a)).unwrap());
+
+    assert!(!tiled.is_empty());
+    assert_eq!(tiled.len(), expected.len());
+    for (a, b) in tiled.iter().zip(&expected) {
+        let (a_box, b_box) = (a.bounding_box(), b.bounding_box());
+        let diffs = [
+            a_box.x - b_box.x,
+            a_box.y - b_box.y,
+            a_box.w - b_box.w,
+            a_box.h - b_box.h,
+        ];
+        assert!(diffs.iter().all(|diff| diff.abs() < 1e-5), "{:?}", diffs);
+        assert_eq!(a.probabilities, b.probabilities);
+    }
+    Ok(())
+}
+
+#[test]
+fn predict_tiled_merges_tiles() -> Result<(), Error> {
+    let mut net = Network::from_cfg_str(CFG, None::<&str>, false)?;
+    let predict = PredictOptions::new().thresh(0.05);
+    let options = TileOptions::new().predict(predict.clone());
+
+    let merged = net.predict_tiled(large_image(), &options);
+    let unmerged = net.predict_tiled(large_image(), &options.clone().nms(None));
+    assert!(!merged.is_empty());
+    assert!(merged.len() < unmerged.len());
+    let scores: Vec<_> = merged.iter().map(score).collect();
+    assert!(scores.iter().all(|&score| score >= 0.05));
+    assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+
+    // the full image pass adds the detections of the downscaled image
+    let tiles_only = net.predict_tiled(large_image(), &options.clone().nms(None).full_image(false));
+    let full = net.predict_with(large_image(), &predict);
+    let full = full
+        .iter()
+        .filter(|detection| detection.best_class(Some(0.0)).is_some());
+    assert_eq!(unmerged.len(), tiles_only.len() + full.count());
+
+    // boxes in pixels are the normalized boxes scaled by the image size
+    let options = options.predict(predict.relative(false).max_detections(3));
+    let pixels = net.predict_tiled(large_image(), &options);
+    assert_eq!(pixels.len(), 3);
+    for (a, b) in pixels.iter().zip(&merged) {
+        let (a, b) = (a.bounding_box(), b.bounding_box().to_pixels(96, 64));
+        let diffs = [a.x - b.x, a.y - b.y, a.w - b.w, a.h - b.h];
+        assert!(diffs.iter().all(|diff| diff.abs() < 1e-3), "{:?}", diffs);
+    }
+    Ok(())
+}
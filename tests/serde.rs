@@ -0,0 +1,89 @@
+#![cfg(feature = "serde")]
+
+use darknet::{
+    Activation, BBox, Error, LayerSummary, LayerType, Network, NmsKind, OwnedDetection, WeightsType,
+};
+
+const CFG: &str = r#"
+[net]
+width=8
+height=8
+channels=3
+
+[convolutional]
+filters=4
+size=3
+stride=2
+pad=1
+activation=leaky
+"#;
+
+#[test]
+fn serialize_kinds_by_name() {
+    let json = serde_json::to_string(&(LayerType::Convolutional, Activation::HardMish)).unwrap();
+    assert_eq!(json, r#"["convolutional","hard_mish"]"#);
+    assert_eq!(
+        serde_json::to_string(&NmsKind::Diou).unwrap(),
+        r#""diounms""#
+    );
+
+    let kind: WeightsType = serde_json::from_str(r#""per_layer""#).unwrap();
+    assert_eq!(kind, WeightsType::PerFeature);
+    // types that cannot be declared in config files still round-trip
+    let type_: LayerType = serde_json::from_str(r#""l2norm""#).unwrap();
+    assert_eq!(type_, LayerType::L2Norm);
+    assert!(serde_json::from_str::<Activation>(r#""sigmoid""#).is_err());
+}
+
+#[test]
+fn serialize_owned_detection() {
+    let detection = OwnedDetection {
+        bbox: BBox {
+            x: 0.5,
+            y: 0.25,
+            w: 0.125,
+            h: 1.0,
+        },
+        objectness: 0.75,
+        probabilities: vec![0.0, 0.5],
+        uc: None,
+        sort_class: 1,
+    };
+    let json = serde_json::to_value(&detection).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "bbox": { "x": 0.5, "y": 0.25, "w": 0.125, "h": 1.0 },
+            "objectness": 0.75,
+            "probabilities": [0.0, 0.5],
+            "uc": null,
+            "sort_class": 1,
+        })
+    );
+
+    let parsed: OwnedDetection = serde_json::from_value(json).unwrap();
+    assert_eq!((parsed.bbox.x, parsed.bbox.h), (0.5, 1.0));
+    assert_eq!(parsed.probabilities, detection.probabilities);
+}
+
+#[test]
+fn serialize_layer_summary() -> Result<(), Error> {
+    let net = Network::from_cfg_str(CFG, None::<&str>, false)?;
+    let summary = net.get_layer(0).unwrap().summary();
+    let json = serde_json::to_value(&summary).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "layer_type": "convolutional",
+            "activation": "leaky",
+            "input_shape": [8, 8, 3],
+            "output_shape": [4, 4, 4],
+            "num_outputs": 64,
+        })
+    );
+    assert_eq!(
+        serde_json::from_value::<LayerSummary>(json).unwrap(),
+        summary
+    );
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use darknet::{BBox, Image, Network};
+use darknet::{BBox, Image, Labels, Network};
 use image::Rgb;
 use sha2::{Digest, Sha256};
 use std::{
@@ -46,11 +46,9 @@ fn main() -> Result<()> {
     }
 
     // Load network & labels
-    let object_labels = std::fs::read_to_string(LABEL_PATH)?
-        .lines()
-        .map(ToOwned::to_owned)
-        .collect::<Vec<_>>();
+    let object_labels = Labels::open(LABEL_PATH)?;
     let mut net = Network::load(CFG_PATH, Some(weights_path), false)?;
+    object_labels.check(&net)?;
 
     // Run object detection
     let image = Image::open(IMAGE_PATH)?;
@@ -61,8 +59,8 @@ fn main() -> Result<()> {
         .iter()
         .filter(|det| det.objectness() > OBJECTNESS_THRESHOLD)
         .flat_map(|det| {
-            det.best_class(Some(CLASS_PROB_THRESHOLD))
-                .map(|(class_index, prob)| (det, prob, &object_labels[class_index]))
+            det.best_class_label(&object_labels, Some(CLASS_PROB_THRESHOLD))
+                .map(|(label, prob)| (det, prob, label))
         })
         .enumerate()
         .for_each(|(index, (det, prob, label))| {
@@ -0,0 +1,240 @@
+mod common;
+
+use common::detection;
+use darknet::{
+    track::{ByteOptions, ByteTracker, SortOptions, SortTracker, Track, TrackState},
+    BoundingBox, OwnedDetection,
+};
+
+/// A box moving right by 4 pixels per frame, and a box moving down by 2 pixels per frame.
+fn moving_boxes(frame: usize) -> [BoundingBox; 2] {
+    let frame = frame as f32;
+    [
+        BoundingBox::new(50.0 + 4.0 * frame, 50.0, 20.0, 40.0),
+        BoundingBox::new(200.0, 100.0 + 2.0 * frame, 30.0, 30.0),
+    ]
+}
+
+fn ids_and_states(tracks: &[Track]) -> Vec<(u64, TrackState)> {
+    tracks.iter().map(|track| (track.id, track.state)).collect()
+}
+
+#[test]
+fn sort_moving_boxes() {
+    let mut tracker = SortTracker::default();
+    let mut tracks = vec![];
+    for frame in 0..20 {
+        let [a, b] = moving_boxes(frame);
+        tracks = tracker.update(vec![detection(b, 1, 0.9), detection(a, 0, 0.8)]);
+        assert_eq!(
+            ids_and_states(&tracks),
+            [(1, TrackState::Confirmed), (2, TrackState::Confirmed)]
+        );
+    }
+    assert_eq!(tracker.frame_count(), 20);
+
+    let [a, b] = moving_boxes(19);
+    let (second, first) = (&tracks[0], &tracks[1]);
+    assert_eq!((first.class, second.class), (0, 1));
+    assert_eq!(
+        (first.age, first.hits, first.time_since_update),
+        (19, 19, 0)
+    );
+    assert!((first.bbox.x - a.x).abs() < 0.5 && (first.bbox.w - a.w).abs() < 0.5);
+    assert!((second.bbox.y - b.y).abs() < 0.5 && (second.bbox.h - b.h).abs() < 0.5);
+    assert!((first.velocity[0] - 4.0).abs() < 0.1 && first.velocity[1].abs() < 0.1);
+    assert!(second.velocity[0].abs() < 0.1 && (second.velocity[1] - 2.0).abs() < 0.1);
+}
+
+#[test]
+fn sort_track_states() {
+    let mut tracker = SortTracker::new(SortOptions {
+        max_age: 2,
+        ..Default::default()
+    });
+    for frame in 0..5 {
+        let [a, _] = moving_boxes(frame);
+        tracker.update(vec![detection(a, 0, 0.9)]);
+    }
+
+    // a new object needs 3 matches to be confirmed
+    let [a, b] = moving_boxes(5);
+    let tracks = tracker.update(vec![detection(a, 0, 0.9), detection(b, 1, 0.9)]);
+    assert_eq!(
+        ids_and_states(&tracks),
+        [(1, TrackState::Confirmed), (2, TrackState::Tentative)]
+    );
+
+    // the first object is lost, and found again in the predicted position
+    let [_, b] = moving_boxes(6);
+    let tracks = tracker.update(vec![detection(b, 1, 0.9)]);
+    assert_eq!(
+        ids_and_states(&tracks),
+        [(1, TrackState::Lost), (2, TrackState::Tentative)]
+    );
+    assert_eq!(tracks[0].time_since_update, 1);
+    let [_, b] = moving_boxes(7);
+    tracker.update(vec![detection(b, 1, 0.9)]);
+    let [a, b] = moving_boxes(8);
+    let tracks = tracker.update(vec![detection(a, 0, 0.9), detection(b, 1, 0.9)]);
+    assert_eq!(
+        ids_and_states(&tracks),
+        [(1, TrackState::Tentative), (2, TrackState::Confirmed)]
+    );
+
+    // lost tracks are removed after the maximum age
+    for _ in 0..3 {
+        tracker.update(Vec::<OwnedDetection>::new());
+    }
+    assert!(tracker.update(Vec::<OwnedDetection>::new()).is_empty());
+    let [a, _] = moving_boxes(13);
+    let tracks = tracker.update(vec![detection(a, 0, 0.9)]);
+    assert_eq!(ids_and_states(&tracks), [(3, TrackState::Tentative)]);
+}
+
+#[test]
+fn sort_class_aware() {
+    let bbox = BoundingBox::new(50.0, 50.0, 20.0, 20.0);
+    let sequence = [detection(bbox, 0, 0.9), detection(bbox, 1, 0.9)];
+
+    let mut tracker = SortTracker::default();
+    let ids: Vec<_> = sequence
+        .iter()
+        .map(|detection| tracker.update(vec![detection.clone()])[0].id)
+        .collect();
+    assert_eq!(ids, [1, 1]);
+
+    let mut tracker = SortTracker::new(SortOptions {
+        class_aware: true,
+        ..Default::default()
+    });
+    tracker.update(vec![sequence[0].clone()]);
+    let tracks = tracker.update(vec![sequence[1].clone()]);
+    assert_eq!(
+        ids_and_states(&tracks),
+        [(1, TrackState::Lost), (2, TrackState::Confirmed)]
+    );
+}
+
+#[test]
+fn byte_low_score_detections() {
+    let mut tracker = ByteTracker::default();
+    let [a, b] = moving_boxes(0);
+    // the low score detection does not start a track
+    let tracks = tracker.update(vec![detection(a, 0, 0.9), detection(b, 1, 0.3)]);
+    assert_eq!(ids_and_states(&tracks), [(1, TrackState::Confirmed)]);
+
+    // but it continues one in the second matching
+    for frame in 1..10 {
+        let [a, _] = moving_boxes(frame);
+        let score = if frame % 2 == 0 { 0.9 } else { 0.2 };
+        let tracks = tracker.update(vec![detection(a, 0, score)]);
+        assert_eq!(ids_and_states(&tracks), [(1, TrackState::Confirmed)]);
+        assert_eq!(tracks[0].score, score);
+    }
+
+    // a detection below the low threshold is ignored
+    let [a, _] = moving_boxes(10);
+    let tracks = tracker.update(vec![detection(a, 0, 0.05)]);
+    assert_eq!(ids_and_states(&tracks), [(1, TrackState::Lost)]);
+}
+
+#[test]
+fn byte_track_states() {
+    let mut tracker = ByteTracker::new(ByteOptions {
+        track_buffer: 3,
+        ..Default::default()
+    });
+    let [a, b] = moving_boxes(0);
+    tracker.update(vec![detection(a, 0, 0.9)]);
+
+    // the tracks after the first frame are confirmed by a second match
+    let [a, _] = moving_boxes(1);
+    let tracks = tracker.update(vec![detection(a, 0, 0.9), detection(b, 1, 0.9)]);
+    assert_eq!(
+        ids_and_states(&tracks),
+        [(1, TrackState::Confirmed), (2, TrackState::Tentative)]
+    );
+    let [a, b] = moving_boxes(2);
+    let tracks = tracker.update(vec![detection(a, 0, 0.9), detection(b, 1, 0.9)]);
+    assert_eq!(
+        ids_and_states(&tracks),
+        [(1, TrackState::Confirmed), (2, TrackState::Confirmed)]
+    );
+
+    // a lost track is found again in the track buffer
+    for frame in 3..6 {
+        let [_, b] = moving_boxes(frame);
+        let tracks = tracker.update(vec![detection(b, 1, 0.9)]);
+        assert_eq!(
+            ids_and_states(&tracks),
+            [(1, TrackState::Lost), (2, TrackState::Confirmed)]
+        );
+    }
+    let [a, b] = moving_boxes(6);
+    let tracks = tracker.update(vec![detection(a, 0, 0.9), detection(b, 1, 0.9)]);
+    assert_eq!(
+        ids_and_states(&tracks),
+        [(1, TrackState::Confirmed), (2, TrackState::Confirmed)]
+    );
+    assert_eq!(tracks[0].time_since_update, 0);
+    assert_eq!(tracks[0].age, 6);
+
+    // and removed after it
+    for _ in 0..4 {
+        tracker.update(Vec::<OwnedDetection>::new());
+    }
+    assert!(tracker.update(Vec::<OwnedDetection>::new()).is_empty());
+
+    // an unmatched tentative track is removed at once
+    let [a, _] = moving_boxes(0);
+    tracker.update(vec![detection(a, 0, 0.9)]);
+    assert!(tracker.update(Vec::<OwnedDetection>::new()).is_empty());
+}
+
+#[test]
+fn deterministic_tracks() {
+    // crossing objects of the same size
+    let sequence: Vec<Vec<OwnedDetection>> = (0..30)
+        .map(|frame| {
+            let offset = 3.0 * frame as f32;
+            vec![
+                detection(BoundingBox::new(10.0 + offset, 50.0, 20.0, 20.0), 0, 0.9),
+                detection(BoundingBox::new(100.0 - offset, 55.0, 20.0, 20.0), 0, 0.6),
+                detection(BoundingBox::new(55.0, 20.0 + offset, 20.0, 20.0), 1, 0.7),
+            ]
+        })
+        .collect();
+
+    let run_sort = || {
+        let mut tracker = SortTracker::default();
+        sequence
+            .iter()
+            .map(|detections| tracker.update(detections.iter().cloned()))
+            .collect::<Vec<_>>()
+    };
+    let run_byte = || {
+        let mut tracker = ByteTracker::default();
+        sequence
+            .iter()
+            .map(|detections| tracker.update(detections.iter().cloned()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(run_sort(), run_sort());
+    assert_eq!(run_byte(), run_byte());
+
+    // the objects keep their IDs through the crossing by their velocities
+    let last = run_byte().pop().unwrap();
+    let confirmed: Vec<_> = last
+        .iter()
+        .filter(|track| track.state == TrackState::Confirmed)
+        .map(|track| {
+            (
+                track.id,
+                track.velocity[0].round(),
+                track.velocity[1].round(),
+            )
+        })
+        .collect();
+    assert_eq!(confirmed, [(1, 3.0, 0.0), (2, -3.0, 0.0), (3, 0.0, 3.0)]);
+}
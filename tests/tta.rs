@@ -0,0 +1,216 @@
+mod common;
+
+use common::detection;
+use darknet::{
+    nms::NmsOptions,
+    tta::{Augmentation, TtaMerge, TtaOptions},
+    BoundingBox, Error, Image, Network, OwnedDetection, PredictOptions,
+};
+use image::{Luma, Rgb, RgbImage};
+
+const CFG: &str = r#"
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=21
+size=3
+stride=4
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=4,4, 8,8, 16,16
+classes=2
+num=3
+"#;
+
+fn box_close(a: &BoundingBox, b: &BoundingBox, tolerance: f32) -> bool {
+    let diffs = [a.x - b.x, a.y - b.y, a.w - b.w, a.h - b.h];
+    diffs.iter().all(|diff| diff.abs() < tolerance)
+}
+
+fn assert_box_close(a: &BoundingBox, b: &BoundingBox, tolerance: f32) {
+    assert!(box_close(a, b, tolerance), "{:?} != {:?}", a, b);
+}
+
+/// Get the normalized box of the pixels above the threshold.
+fn bright_box(image: &Image, threshold: f32) -> BoundingBox {
+    let (width, height) = (image.width(), image.height());
+    let data = image.get_data();
+    let (mut left, mut top, mut right, mut bottom) = (width, height, 0, 0);
+    for y in 0..height {
+        for x in 0..width {
+            if data[y * width + x] > threshold {
+                left = left.min(x);
+                top = top.min(y);
+                right = right.max(x + 1);
+                bottom = bottom.max(y + 1);
+            }
+        }
+    }
+    BoundingBox::from_xyxy(left as f32, top as f32, right as f32, bottom as f32)
+        .to_normalized(width, height)
+}
+
+#[test]
+fn augmentations_round_trip() {
+    // a white square on black
+    let buffer = image::GrayImage::from_fn(80, 40, |x, y| {
+        let inside = (12..20).contains(&x) && (8..16).contains(&y);
+        Luma([if inside { 255 } else { 0 }])
+    });
+    let image = Image::from(&buffer);
+    let original = bright_box(&image, 0.75);
+    assert_box_close(&original, &BoundingBox::new(0.2, 0.3, 0.1, 0.2), 1e-6);
+
+    for &augmentation in &[
+        Augmentation::IDENTITY,
+        Augmentation::horizontal_flip(),
+        Augmentation::scale(0.5),
+        Augmentation::scale(1.5),
+        Augmentation::new(true, 0.75),
+    ] {
+        let augmented = augmentation.apply(&image);
+        assert_eq!(augmented.shape(), image.shape());
+        let bbox = bright_box(&augmented, 0.75);
+        let restored = augmentation.to_image(&bbox, 80, 40);
+        // resampling may move the edges by a pixel
+        assert_box_close(&restored, &original, 2.0 / 40.0);
+    }
+
+    // the flip mirrors the pixels, and the padding is gray
+    let flipped = Augmentation::horizontal_flip().apply(&image);
+    assert_box_close(
+        &bright_box(&flipped, 0.75),
+        &BoundingBox::new(0.8, 0.3, 0.1, 0.2),
+        1e-6,
+    );
+    let shrunk = Augmentation::scale(0.5).apply(&image);
+    assert_eq!(shrunk.get_data()[0], 0.5);
+    assert_box_close(
+        &Augmentation::scale(0.5).to_image(&BoundingBox::new(0.5, 0.5, 0.5, 0.5), 80, 40),
+        &BoundingBox::new(0.5, 0.5, 1.0, 1.0),
+        1e-6,
+    );
+}
+
+#[test]
+fn merge_augmentations() {
+    let a = BoundingBox::new(0.4, 0.5, 0.2, 0.2);
+    let b = BoundingBox::new(0.42, 0.5, 0.2, 0.2);
+    let augmentations = vec![
+        vec![detection(a, 0, 0.9), detection(a, 1, 0.0)],
+        vec![
+            detection(b, 0, 0.7),
+            detection(BoundingBox::new(0.8, 0.8, 0.1, 0.1), 1, 0.4),
+        ],
+    ];
+
+    // NMS keeps the best of the overlapping boxes, and removes the empty detection
+    let merged = TtaMerge::default().merge(&augmentations);
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged[0].best_class(None), Some((0, 0.9)));
+    assert_box_close(&merged[0].bounding_box(), &a, 1e-6);
+    assert_eq!(merged[1].best_class(None), Some((1, 0.4)));
+
+    // WBF averages the boxes of both augmentations, and halves the score of the single box
+    let merged = TtaMerge::WeightedBoxFusion {
+        iou_threshold: 0.55,
+        score_threshold: 0.0,
+    }
+    .merge(&augmentations);
+    assert_eq!(merged.len(), 2);
+    let (class, score) = merged[0].best_class(None).unwrap();
+    assert_eq!(class, 0);
+    assert!((score - 0.8).abs() < 1e-6);
+    let x = (0.4 * 0.9 + 0.42 * 0.7) / 1.6;
+    assert_box_close(
+        &merged[0].bounding_box(),
+        &BoundingBox::new(x, 0.5, 0.2, 0.2),
+        1e-6,
+    );
+    let (class, score) = merged[1].best_class(None).unwrap();
+    assert_eq!(class, 1);
+    assert!((score - 0.2).abs() < 1e-6);
+}
+
+#[test]
+fn default_tta_options() {
+    let options = TtaOptions::new();
+    assert_eq!(
+        options.augmentations,
+        [
+            Augmentation::IDENTITY,
+            Augmentation::new(true, 0.83),
+            Augmentation::scale(0.67)
+        ]
+    );
+    assert_eq!(options.merge, TtaMerge::Nms(NmsOptions::default()));
+    assert_eq!(options.predict, PredictOptions::default());
+}
+
+#[test]
+fn predict_augmentations() -> Result<(), Error> {
+    let mut net = Network::from_cfg_str(CFG, None::<&str>, false)?;
+    let image = RgbImage::from_fn(48, 32, |x, y| Rgb([(x * 5) as u8, (y * 7) as u8, 128]));
+    let predict = PredictOptions::new().thresh(0.05);
+
+    // the identity with NMS that suppresses nothing is the plain prediction
+    let options = TtaOptions::new()
+        .augmentations(vec![Augmentation::IDENTITY])
+        .merge(TtaMerge::Nms(NmsOptions {
+            iou_threshold: 1.0,
+            ..NmsOptions::default()
+        }))
+        .predict(predict.clone());
+    let detections = net.predict_tta(&image, &options);
+    let single = net.predict_with(&image, &predict);
+    let single: Vec<_> = single
+        .iter()
+        .map(OwnedDetection::from)
+        .filter(|detection| detection.best_class(Some(0.0)).is_some())
+        .collect();
+    assert!(!single.is_empty());
+    assert_eq!(detections.len(), single.len());
+    for detection in &single {
+        assert!(detections.iter().any(|other| {
+            other.probabilities == detection.probabilities
+                && box_close(&other.bounding_box(), &detection.bounding_box(), 1e-6)
+        }));
+    }
+
+    // the flipped boxes are mirrored back
+    let flipped = Augmentation::horizontal_flip().apply(&Image::from(&image));
+    let expected = net.predict_with(&flipped, &predict);
+    let expected: Vec<_> = expected
+        .iter()
+        .map(OwnedDetection::from)
+        .filter(|detection| detection.best_class(Some(0.0)).is_some())
+        .collect();
+    let options = options.augmentations(vec![Augmentation::horizontal_flip()]);
+    let detections = net.predict_tta(&image, &options);
+    assert_eq!(detections.len(), expected.len());
+    for detection in &expected {
+        let mut bbox = detection.bounding_box();
+        bbox.x = 1.0 - bbox.x;
+        assert!(detections.iter().any(|other| {
+            other.probabilities == detection.probabilities
+                && box_close(&other.bounding_box(), &bbox, 1e-6)
+        }));
+    }
+
+    // the merged detections are sorted and limited
+    let options = TtaOptions::new().predict(predict.max_detections(4));
+    let detections = net.predict_tta(&image, &options);
+    assert_eq!(detections.len(), 4);
+    let scores: Vec<_> = detections
+        .iter()
+        .map(|detection| detection.best_class(None).unwrap().1)
+        .collect();
+    assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+    Ok(())
+}
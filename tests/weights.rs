@@ -0,0 +1,244 @@
+mod common;
+
+use common::write_temp;
+use darknet::{
+    cfg::Config,
+    weights::{Weights, WeightsHeader},
+    Error, LayerType, Network,
+};
+use std::{env, fs};
+
+const CFG: &str = r#"
+[net]
+width=8
+height=8
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=4
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[maxpool]
+size=2
+stride=2
+
+[connected]
+output=2
+activation=linear
+"#;
+
+/// Floats of the conv layer: 4 biases, 3x4 batchnorm values and 4x3x3x3 weights.
+const CONV_FLOATS: usize = 4 + 3 * 4 + 4 * 3 * 3 * 3;
+/// Floats of the connected layer: 2 biases and 2x(4x4x4) weights.
+const CONNECTED_FLOATS: usize = 2 + 2 * 4 * 4 * 4;
+
+fn weights_bytes(version: [i32; 3], seen: u64, floats: usize) -> Vec<u8> {
+    let mut bytes = vec![];
+    version
+        .iter()
+        .for_each(|word| bytes.extend(&word.to_le_bytes()));
+    if version[0] * 10 + version[1] >= 2 {
+        bytes.extend(&seen.to_le_bytes());
+    } else {
+        bytes.extend(&(seen as u32).to_le_bytes());
+    }
+    (0..floats).for_each(|index| bytes.extend(&(index as f32).to_le_bytes()));
+    bytes
+}
+
+#[test]
+fn read_header() -> Result<(), Error> {
+    let bytes = weights_bytes([0, 2, 5], 64_000, 0);
+    let header = WeightsHeader::read(&mut bytes.as_slice())?;
+    assert_eq!(
+        header,
+        WeightsHeader {
+            major: 0,
+            minor: 2,
+            revision: 5,
+            seen: 64_000
+        }
+    );
+
+    // version 0.1 stores a 32-bit counter
+    let bytes = weights_bytes([0, 1, 0], 320, 0);
+    assert_eq!(WeightsHeader::read(&mut bytes.as_slice())?.seen, 320);
+
+    let result = WeightsHeader::read(&mut &bytes[..14]);
+    assert!(matches!(result, Err(Error::InvalidWeightsError { .. })));
+    Ok(())
+}
+
+#[test]
+fn read_layer_tensors() -> Result<(), Error> {
+    let config: Config = CFG.parse()?;
+    let bytes = weights_bytes([0, 2, 0], 128, CONV_FLOATS + CONNECTED_FLOATS);
+    let weights = Weights::from_reader(bytes.as_slice(), &config)?;
+
+    assert_eq!(weights.header.seen, 128);
+    assert_eq!(weights.cutoff, None);
+    assert_eq!(weights.num_floats(), CONV_FLOATS + CONNECTED_FLOATS);
+    assert_eq!(weights.layers.len(), 2);
+
+    let conv = &weights.layers[0];
+    assert_eq!((conv.index, conv.layer_type), (0, LayerType::Convolutional));
+    let names: Vec<_> = conv.tensors.iter().map(|tensor| tensor.name).collect();
+    assert_eq!(
+        names,
+        [
+            "biases",
+            "scales",
+            "rolling_mean",
+            "rolling_variance",
+            "weights"
+        ]
+    );
+    let kernel = conv.get("weights").unwrap();
+    assert_eq!(kernel.shape, [4, 3, 3, 3]);
+    assert_eq!(kernel.data[0], 16.0);
+
+    let connected = &weights.layers[1];
+    assert_eq!(
+        (connected.index, connected.layer_type),
+        (2, LayerType::Connected)
+    );
+    assert_eq!(connected.get("weights").unwrap().shape, [2, 64]);
+    assert_eq!(connected.get("biases").unwrap().data, [124.0, 125.0]);
+    Ok(())
+}
+
+#[test]
+fn read_partial_weights() -> Result<(), Error> {
+    let config: Config = CFG.parse()?;
+    let bytes = weights_bytes([0, 2, 0], 0, CONV_FLOATS);
+    let weights = Weights::from_reader(bytes.as_slice(), &config)?;
+    assert_eq!(weights.layers.len(), 1);
+    assert_eq!(weights.cutoff, Some(2));
+    Ok(())
+}
+
+#[test]
+fn mismatched_weights() -> Result<(), Error> {
+    let config: Config = CFG.parse()?;
+
+    let bytes = weights_bytes([0, 2, 0], 0, CONV_FLOATS + 1);
+    let result = Weights::from_reader(bytes.as_slice(), &config);
+    assert!(matches!(result, Err(Error::InvalidWeightsError { .. })));
+
+    let bytes = weights_bytes([0, 2, 0], 0, CONV_FLOATS + CONNECTED_FLOATS + 1);
+    let result = Weights::from_reader(bytes.as_slice(), &config);
+    assert!(matches!(result, Err(Error::InvalidWeightsError { .. })));
+    Ok(())
+}
+
+#[test]
+fn load_mismatched_weights() {
+    let cfg = write_temp("weights-mismatched.cfg", CFG.as_bytes());
+    let weights = write_temp(
+        "weights-mismatched.weights",
+        weights_bytes([0, 2, 0], 0, CONV_FLOATS + 3),
+    );
+    let result = Network::load(&cfg, Some(&weights), false);
+    assert!(matches!(result, Err(Error::InvalidWeightsError { .. })));
+}
+
+#[test]
+fn load_weights_into_backbone() -> Result<(), Error> {
+    // libdarknet ignores the weights after the last layer of the config
+    let backbone = CFG.split("[maxpool]").next().unwrap();
+    let cfg = write_temp("weights-backbone.cfg", backbone);
+    let weights = write_temp(
+        "weights-backbone.weights",
+        weights_bytes([0, 2, 0], 0, CONV_FLOATS + CONNECTED_FLOATS),
+    );
+    let net = Network::load(&cfg, Some(&weights), false)?;
+    assert_eq!(net.num_layers(), 1);
+    Ok(())
+}
+
+#[test]
+fn save_and_reload_weights() -> Result<(), Error> {
+    let config: Config = CFG.parse()?;
+    let bytes = weights_bytes([0, 2, 5], 128, CONV_FLOATS + CONNECTED_FLOATS);
+    let net = Network::from_bytes(CFG.as_bytes(), Some(&bytes), false)?;
+
+    let mut written = vec![];
+    net.write_weights(&mut written)?;
+    assert_eq!(written, bytes);
+
+    let path = env::temp_dir().join("darknet-rust-weights-saved.weights");
+    net.save_weights_upto(&path, 1)?;
+    let weights = Weights::open(&path, &config)?;
+    assert_eq!(weights.cutoff, Some(2));
+    assert_eq!(weights.num_floats(), CONV_FLOATS);
+
+    let result = net.save_weights("./no-such-dir/model.weights");
+    assert!(matches!(result, Err(Error::WeightsCreateError { .. })));
+    Ok(())
+}
+
+#[test]
+fn save_seen_of_whole_batches() -> Result<(), Error> {
+    let cfg = write_temp("weights-seen.cfg", CFG);
+    let bytes = weights_bytes([0, 2, 5], 129, CONV_FLOATS + CONNECTED_FLOATS);
+    let mut net = Network::load_with_batch(&cfg, None::<&str>, false, 2)?;
+    net.read_weights_upto(bytes.as_slice(), usize::MAX)?;
+
+    // like libdarknet, seen is recomputed from the 64 iterations of 2 images
+    let mut written = vec![];
+    net.write_weights(&mut written)?;
+    assert_eq!(WeightsHeader::read(&mut written.as_slice())?.seen, 128);
+    assert_eq!(written[20..], bytes[20..]);
+    Ok(())
+}
+
+#[test]
+fn read_flipped_weights_like_libdarknet() -> Result<(), Error> {
+    let cfg = CFG.replace("activation=leaky", "activation=leaky\nflipped=1");
+    let bytes = weights_bytes([0, 2, 5], 128, CONV_FLOATS + CONNECTED_FLOATS);
+    let weights = Weights::from_reader(bytes.as_slice(), &cfg.parse()?)?;
+    assert_eq!(weights.layers[0].get("weights").unwrap().shape, [27, 4]);
+
+    let mut loaded = vec![];
+    Network::from_bytes(cfg.as_bytes(), Some(&bytes), false)?.write_weights(&mut loaded)?;
+    let mut net = Network::from_bytes(cfg.as_bytes(), None, false)?;
+    net.read_weights_upto(bytes.as_slice(), usize::MAX)?;
+    let mut read = vec![];
+    net.write_weights(&mut read)?;
+    assert_eq!(read, loaded);
+    assert_ne!(read, bytes);
+    Ok(())
+}
+
+#[test]
+fn load_partial_weights() -> Result<(), Error> {
+    let config: Config = CFG.parse()?;
+    let bytes = weights_bytes([0, 2, 0], 0, CONV_FLOATS + CONNECTED_FLOATS);
+    let mut weights = Weights::from_reader(bytes.as_slice(), &config)?;
+    weights.truncate(1);
+    assert_eq!(weights.cutoff, Some(1));
+
+    let path = env::temp_dir().join("darknet-rust-weights-partial.weights");
+    weights.save(&path)?;
+    assert_eq!(
+        fs::read(&path).unwrap(),
+        &bytes[..20 + CONV_FLOATS * 4],
+        "a partial file is a prefix of the full file"
+    );
+
+    let mut net = Network::from_cfg_str(CFG, None::<&str>, false)?;
+    assert_eq!(net.load_weights_upto(&path, 3)?, 2);
+    assert_eq!(net.read_weights_upto(bytes.as_slice(), 1)?, 1);
+
+    let mut written = vec![];
+    net.write_weights_upto(&mut written, 1)?;
+    assert_eq!(written[20..], bytes[20..20 + CONV_FLOATS * 4]);
+
+    let result = net.read_weights_upto(&bytes[..100], 3);
+    assert!(matches!(result, Err(Error::InvalidWeightsError { .. })));
+    Ok(())
+}